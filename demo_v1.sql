@@ -1,5 +1,6 @@
 -- Enum types.
-create type overall_report_type as enum(
+-- Generated by `cargo r -- schema` at `route_verification_server_loader/`.
+create type overall_report_type as enum (
 	'ok',
 	'skip',
 	'unrecorded',
@@ -43,7 +44,22 @@ create type report_item_type as enum (
 	'rpsl_filter',
 	'rpsl_regex',
 	'rpsl_unknown_filter',
-	'recursion'
+	'rec_check_filter',
+	'rec_filter_route_set',
+	'rec_filter_route_set_member',
+	'rec_filter_as_set',
+	'rec_filter_as_name',
+	'rec_filter_and',
+	'rec_filter_or',
+	'rec_filter_not',
+	'rec_check_set_member',
+	'rec_check_remote_as',
+	'rec_remote_as_name',
+	'rec_remote_as_set',
+	'rec_remote_peering_set',
+	'rec_peering_and',
+	'rec_peering_or',
+	'rec_peering_except'
 );
 -- Tables.
 create table if not exists rpsl_obj(
@@ -64,31 +80,202 @@ create table if not exists rpsl_obj_mnt_by(
 	mntner_name text not null references maintainer,
 	primary key (rpsl_obj_name, mntner_name)
 );
-create table if not exists autonomous_system( #!
+create table if not exists autonomous_system(
 	as_num int primary key
 );
+-- Organization from CAIDA AS-to-Organization files, loaded by `cargo r -- as2org`.
+create table if not exists organization(
+	org_id text primary key,
+	org_name text not null,
+	country text not null,
+	-- Registry the record came from, e.g., `ARIN`.
+	source text not null,
+	recorded_time timestamp not null default now()
+);
+-- Organization holding each AS.
+create table if not exists as_organization(
+	as_num int primary key references autonomous_system,
+	org_id text not null references organization,
+	as_name text not null,
+	source text not null,
+	recorded_time timestamp not null default now()
+);
+create index if not exists as_organization_org on as_organization(org_id);
+-- Other ASes held by the same organization.
+create or replace view as_sibling as
+select a.as_num, b.as_num as sibling, a.org_id
+from as_organization a
+join as_organization b on b.org_id = a.org_id and b.as_num <> a.as_num;
 create table if not exists aut_num(
 	as_num int primary key references autonomous_system,
 	as_name text not null,
-	imports json not null,
-	exports json not null,
+	imports jsonb not null,
+	exports jsonb not null,
 	rpsl_obj_name text not null references rpsl_obj
 );
-create table if not exists observed_route(   #!
-	_id serial primary key,
-	raw_observed_routeline text not null,
+-- `jsonb_path_ops` GIN indexes serve containment (`@>`) queries over the IR.
+create index if not exists aut_num_imports on aut_num using gin (imports jsonb_path_ops);
+create index if not exists aut_num_exports on aut_num using gin (exports jsonb_path_ops);
+-- Rule of an aut-num's `mp-import` or `mp-export` policy,
+-- flattened from the IR in `aut_num.imports` and `aut_num.exports`.
+create table if not exists policy_rule(
+	policy_rule_id serial primary key,
+	as_num int not null references aut_num,
+	import bool not null,
+	-- Position among the aut-num's import or export rules in the IR,
+	-- which groups rules by address family and cast.
+	rule_index int not null,
+	address_family text not null check (address_family in ('any', 'ipv4', 'ipv6')),
+	cast_type text not null check (cast_type in ('any', 'unicast', 'multicast')),
+	-- IR of the `mp-filter`.
+	filter jsonb not null,
+	unique (as_num, import, rule_index)
+);
+create index if not exists policy_rule_filter on policy_rule using gin (filter jsonb_path_ops);
+-- Peering a policy rule applies to, with the actions taken on it.
+create table if not exists policy_peering(
+	policy_rule int not null references policy_rule on delete cascade,
+	-- Position among the rule's peerings.
+	peering_index int not null,
+	-- IR of each part of the `mp-peering`.
+	remote_as jsonb not null,
+	remote_router jsonb,
+	local_router jsonb,
+	actions jsonb not null,
+	primary key (policy_rule, peering_index)
+);
+create index if not exists policy_peering_remote_as on policy_peering using gin (remote_as jsonb_path_ops);
+-- Object referenced by the policy of an aut-num, filter-set, or peering-set,
+-- whether or not the object is defined.
+create table if not exists policy_reference(
+	policy_reference_id serial primary key,
+	-- The referencing object.
+	rpsl_obj_name text not null references rpsl_obj,
+	-- Referencing aut-num rule, null for filter-sets and peering-sets.
+	policy_rule int references policy_rule on delete cascade,
+	-- Whether the reference is in a peering rather than a filter.
+	in_peering bool not null,
+	referenced_type text not null check (
+		referenced_type in ('as_num', 'as_set', 'route_set', 'filter_set', 'peering_set')
+	),
+	-- RPSL name of the referenced object, e.g., `AS3356` or `AS-FOO`.
+	referenced_name text not null
+);
+create index if not exists policy_reference_referenced on policy_reference(referenced_name, referenced_type);
+create table if not exists verification_run(
+	run_id serial primary key,
+	-- Names of the `Verbosity` flags turned on.
+	verbosity text[] not null,
+	ir_dir_hash text not null,
+	as_rel_file text not null,
+	-- SHA-256 of `as_rel_file`.
+	as_rel_hash text not null,
+	-- MRT, update dump, or text export the routes were read from,
+	-- or `verification_run <run_id>` for runs re-verifying another run.
+	input_file text not null,
+	verifier_version text not null,
+	loader_version text not null,
+	start_time timestamp not null default now(),
+	-- Null until the run finishes.
+	end_time timestamp,
+	n_observed_route int not null default 0,
+	n_exchange_report int not null default 0,
+	-- Observed routes whose route was already verified with the same IR,
+	-- AS relationships, verifier, and verbosity.
+	n_cache_hit int not null default 0,
+	n_failed int not null default 0,
+	-- Number of input entries processed as of the last checkpoint.
+	-- A resumed run skips them.
+	checkpoint bigint not null default 0,
+	-- Byte offset of the input record holding entry `checkpoint` or one before,
+	-- and the position of the record's first entry,
+	-- so a resumed run seeks there instead of decoding the input again.
+	-- Null for inputs that cannot seek, e.g., `.bz2` files and `bgpdump` output,
+	-- whose resumed runs decode and skip the first `checkpoint` entries.
+	checkpoint_offset bigint,
+	checkpoint_offset_index bigint
+);
+-- Unique prefix and AS path, shared by all observations of it.
+create table if not exists route_path(
+	route_path_id serial primary key,
+	-- SHA-256 of the prefix and AS path.
+	content_hash text not null unique,
 	address_prefix inet not null,
-	recorded_time timestamp not null default now()
+	-- AS path with prepending removed, AS sets in braces.
+	as_path text not null
+);
+-- Verification of a route path against one IR, with its summary.
+create table if not exists path_verification(
+	path_verification_id serial primary key,
+	route_path_id int not null references route_path,
+	-- `ir_dir_hash` of the IR verified against.
+	ir_dir_hash text not null,
+	-- `as_rel_hash` of the AS relationships verified with.
+	as_rel_hash text not null,
+	-- `verifier_version` and `verbosity` of the run that verified the path;
+	-- a different verifier or verbosity verifies the path again.
+	verifier_version text not null,
+	verbosity text[] not null,
+	-- Run that verified the path.
+	verification_run int not null references verification_run,
+	-- Null if the route has no reports.
+	worst_type overall_report_type,
+	n_ok int not null,
+	n_skip int not null,
+	n_unrecorded int not null,
+	n_special_case int not null,
+	n_bad int not null,
+	-- `hop_index` of the first bad report as the route propagated.
+	first_bad_hop int,
+	-- Every hop checked and all reports ok.
+	fully_verified bool not null,
+	unique (route_path_id, ir_dir_hash, as_rel_hash, verifier_version, verbosity)
 );
-create table if not exists exchange_report(  #!
+create index if not exists path_verification_worst_type on path_verification(worst_type);
+create index if not exists path_verification_fully_verified on path_verification(fully_verified);
+create table if not exists observed_route(
+	observed_route_id serial primary key,
+	raw_line text not null,
+	address_prefix inet not null,
+	-- Null for withdrawals, which have no AS path to verify.
+	path_verification int references path_verification,
+	-- Run that observed the route.
+	verification_run int not null references verification_run,
+	-- Position of the entry in the input of the run.
+	input_index bigint not null,
+	recorded_time timestamp not null default now(),
+	-- Whether the route was withdrawn instead of announced.
+	withdrawal bool not null default false,
+	-- Time and peer of the BGP update, for routes read from update dumps.
+	observed_time timestamptz,
+	peer_ip inet,
+	peer_as bigint,
+	check (withdrawal = (path_verification is null)),
+	unique (verification_run, input_index)
+);
+create index if not exists observed_route_update on observed_route(address_prefix, peer_ip, observed_time);
+create index if not exists observed_route_path_verification on observed_route(path_verification, observed_route_id);
+create table if not exists exchange_report(
 	report_id serial primary key,
 	from_as int not null references autonomous_system,
 	to_as int not null references autonomous_system,
 	import bool not null,
 	overall_type overall_report_type not null,
-	parent_observed_route int not null references observed_route,
+	parent_verification int not null references path_verification,
+	verification_run int not null references verification_run,
+	-- Position in the verifier's report list for the route.
+	report_index int not null,
+	-- Position of `from_as` in the AS path with prepending removed,
+	-- counting from the collector's peer.
+	hop_index int,
 	recorded_time timestamp not null default now()
 );
+-- Keyset pagination walks reports in `(recorded_time, report_id)` order.
+create index if not exists exchange_report_time on exchange_report(recorded_time, report_id);
+create index if not exists exchange_report_overall_type_time on exchange_report(overall_type, recorded_time, report_id);
+create index if not exists exchange_report_verification_time on exchange_report(parent_verification, recorded_time, report_id);
+create index if not exists exchange_report_from_as_time on exchange_report(from_as, recorded_time, report_id);
+create index if not exists exchange_report_to_as_time on exchange_report(to_as, recorded_time, report_id);
 create table if not exists report_item(
 	report_item_id serial primary key,
 	category overall_report_type not null,
@@ -98,31 +285,116 @@ create table if not exists report_item(
 	num_content int,
 	parent_report int not null references exchange_report
 );
+create index if not exists report_item_parent_report on report_item(parent_report);
+create index if not exists report_item_specific_case on report_item(specific_case, parent_report);
+-- Hop whose report changed between two verification runs,
+-- written by `cargo r -- diff`.
+create table if not exists report_diff(
+	report_diff_id serial primary key,
+	old_run int not null references verification_run,
+	new_run int not null references verification_run,
+	route_path_id int not null references route_path,
+	hop_index int,
+	from_as int not null,
+	to_as int not null,
+	import bool not null,
+	-- Null if the hop has no report in that run.
+	old_type overall_report_type,
+	new_type overall_report_type,
+	-- Report items as `[{"specific_case": …, "str_content": …, "num_content": …}]`.
+	-- An item repeated in a report is listed as many times as it changed.
+	added_items jsonb not null,
+	removed_items jsonb not null
+);
+create index if not exists report_diff_runs on report_diff(old_run, new_run);
+-- CAIDA AS relationship file loaded by `cargo r -- asrel`.
+create table if not exists as_rel_source(
+	as_rel_source_id serial primary key,
+	file_name text not null,
+	-- Snapshot date from the file name, e.g., `20230701.as-rel.bz2`.
+	snapshot_date date not null,
+	-- SHA-256 of the file.
+	file_hash text not null unique,
+	loaded_time timestamp not null default now()
+);
+create index if not exists as_rel_source_snapshot on as_rel_source(snapshot_date);
+-- Tier-1 clique listed in an AS relationship file,
+-- used by the verifier for its tier-1 special cases.
+create table if not exists tier1(
+	as_num int not null references autonomous_system,
+	as_rel_source int not null references as_rel_source,
+	primary key (as_num, as_rel_source)
+);
 create table if not exists provide_customer(
 	provider int not null references autonomous_system,
 	customer int not null references autonomous_system,
+	as_rel_source int not null references as_rel_source,
 	recorded_time timestamp not null default now(),
-	primary key (provider, customer)
+	primary key (provider, customer, as_rel_source)
 );
 create table if not exists peering_set(
 	peering_set_name text primary key references rpsl_obj,
-	peerings json not null
+	peerings jsonb not null
 );
+create index if not exists peering_set_peerings on peering_set using gin (peerings jsonb_path_ops);
 create table if not exists filter_set(
 	filter_set_name text primary key references rpsl_obj,
-	filters json not null
+	filters jsonb not null
 );
-create table if not exists route_obj(   #for a given as, 
+create index if not exists filter_set_filters on filter_set using gin (filters jsonb_path_ops);
+create table if not exists route_obj(
 	address_prefix inet primary key,
 	origin int not null references autonomous_system,
 	rpsl_obj_name text not null references rpsl_obj
 );
+-- Routes of each AS in the IR's `as_routes`, in IR order,
+-- so the IR can be read back from the database.
+create table if not exists as_route(
+	as_num int not null,
+	route_index int not null,
+	address_prefix inet not null,
+	primary key (as_num, route_index)
+);
+-- Peer-to-peer link, stored once with `peer_1 < peer_2`.
 create table if not exists peer(
 	peer_1 int not null references autonomous_system,
 	peer_2 int not null references autonomous_system,
+	as_rel_source int not null references as_rel_source,
 	recorded_time timestamp not null default now(),
-	primary key (peer_1, peer_2)
+	primary key (peer_1, peer_2, as_rel_source),
+	check (peer_1 < peer_2)
+);
+-- Place of each AS in the hierarchy of an AS relationship source,
+-- computed after loading the source.
+create table if not exists as_hierarchy(
+	as_rel_source int not null references as_rel_source,
+	as_num int not null references autonomous_system,
+	-- Number of ASes in the customer cone, including the AS itself.
+	cone_size int not null,
+	-- Provider-to-customer hops from the nearest tier-1 AS,
+	-- null if no provider chain reaches the clique.
+	clique_depth int,
+	primary key (as_rel_source, as_num)
 );
+-- ASes in the customer cone of `as_num`, including itself.
+create table if not exists customer_cone(
+	as_rel_source int not null references as_rel_source,
+	as_num int not null references autonomous_system,
+	member int not null references autonomous_system,
+	primary key (as_rel_source, as_num, member)
+);
+-- Relationship of `as_1` to `as_2` in each AS relationship source,
+-- listed from both sides, e.g., `p2c` from a provider to its customer
+-- and `c2p` back.
+create or replace view as_relationship as
+select as_rel_source, provider as as_1, customer as as_2, 'p2c' as relationship
+from provide_customer
+union all
+select as_rel_source, customer, provider, 'c2p' from provide_customer
+union all
+select as_rel_source, peer_1, peer_2, 'p2p' from peer
+union all
+select as_rel_source, peer_2, peer_1, 'p2p' from peer;
 create table if not exists as_set(
 	as_set_name text primary key references rpsl_obj,
 	is_any boolean not null default false
@@ -143,7 +415,9 @@ create table if not exists mbrs_by_ref(
 	primary key (rpsl_obj_name, mntner_name)
 );
 create table if not exists route_set(
-	route_set_name text primary key references rpsl_obj
+	route_set_name text primary key references rpsl_obj,
+	-- IR of the members, keeping their range operators.
+	members jsonb not null default '[]'
 );
 create table if not exists route_set_contains_address_prefix(
 	route_set_name text not null references route_set,
@@ -154,4 +428,31 @@ create table if not exists route_set_contains_set(
 	route_set_name text not null references route_set,
 	contained_set text not null,
 	primary key (route_set_name, contained_set)
-);
\ No newline at end of file
+);
+-- Policy references to objects not defined in the loaded IR.
+create or replace view undefined_reference as
+select r.*
+from policy_reference r
+where not case r.referenced_type
+	when 'as_num' then exists (select 1 from aut_num a where a.rpsl_obj_name = r.referenced_name)
+	when 'as_set' then exists (select 1 from as_set s where s.as_set_name = r.referenced_name)
+	when 'route_set' then exists (select 1 from route_set s where s.route_set_name = r.referenced_name)
+	when 'filter_set' then exists (select 1 from filter_set s where s.filter_set_name = r.referenced_name)
+	when 'peering_set' then exists (select 1 from peering_set s where s.peering_set_name = r.referenced_name)
+end;
+-- RPSL class of each stored object, which its body does not repeat.
+create or replace view rpsl_obj_class as
+select mntner_name as rpsl_obj_name, 'mntner' as class from mntner_obj
+union all
+select rpsl_obj_name, case family(address_prefix) when 6 then 'route6' else 'route' end
+from route_obj
+union all
+select rpsl_obj_name, 'aut-num' from aut_num
+union all
+select as_set_name, 'as-set' from as_set
+union all
+select route_set_name, 'route-set' from route_set
+union all
+select peering_set_name, 'peering-set' from peering_set
+union all
+select filter_set_name, 'filter-set' from filter_set;
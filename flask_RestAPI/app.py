@@ -4,6 +4,7 @@ from flask import Flask, jsonify, render_template
 from psycopg.errors import InvalidTextRepresentation
 from psycopg.rows import dict_row
 
+# Keep in sync with `route_verification_server_loader/src/report_type.rs`.
 OVERALL_REPORT_TYPES = ("ok", "skip", "unrecorded", "special_case", "bad")
 REPORT_ITEM_TYPES = (
     "skip_regex_tilde",
@@ -42,7 +43,22 @@ REPORT_ITEM_TYPES = (
     "rpsl_filter",
     "rpsl_regex",
     "rpsl_unknown_filter",
-    "recursion",
+    "rec_check_filter",
+    "rec_filter_route_set",
+    "rec_filter_route_set_member",
+    "rec_filter_as_set",
+    "rec_filter_as_name",
+    "rec_filter_and",
+    "rec_filter_or",
+    "rec_filter_not",
+    "rec_check_set_member",
+    "rec_check_remote_as",
+    "rec_remote_as_name",
+    "rec_remote_as_set",
+    "rec_remote_peering_set",
+    "rec_peering_and",
+    "rec_peering_or",
+    "rec_peering_except",
 )
 
 
@@ -72,7 +88,7 @@ def get_rpsl_obj_by_name(rpsl_obj_name):
 def get_verification_reports(observed_route_id):
     with conn.cursor() as cur:
         reports = cur.execute(
-            "SELECT * FROM exchange_report WHERE parent_observed_route = %s",
+            "SELECT * FROM exchange_report WHERE parent_verification = (SELECT path_verification FROM observed_route WHERE observed_route_id = %s)",
             (observed_route_id,),
         ).fetchall()
     return jsonify(reports)
@@ -156,7 +172,7 @@ SELECT
     count(*) AS report_count
 FROM exchange_report
 JOIN provide_customer ON from_as = customer AND to_as = provider
-JOIN observed_route ON parent_observed_route = observed_route_id
+JOIN observed_route ON parent_verification = path_verification
 WHERE overall_type = %s AND import = true
 GROUP BY
     observed_route_id, raw_line, address_prefix, observed_route.recorded_time
@@ -302,7 +318,7 @@ SELECT
 FROM
     exchange_report e
 JOIN
-    observed_route r ON e.parent_observed_route = r.observed_route_id
+    observed_route r ON e.parent_verification = r.path_verification
 LEFT JOIN
     report_item ri ON e.report_id = ri.parent_report
 WHERE
@@ -353,7 +369,7 @@ SELECT
 FROM
     exchange_report e
 JOIN
-    observed_route r ON e.parent_observed_route = r.observed_route_id
+    observed_route r ON e.parent_verification = r.path_verification
 JOIN
     report_item ri ON e.report_id = ri.parent_report
 WHERE
@@ -395,7 +411,7 @@ def get_for_address_prefix(address: str, prefix_length: int):
     JOIN
         report_item ri ON e.report_id = ri.parent_report
     WHERE
-        e.parent_observed_route = (SELECT observed_route_id FROM observed_route WHERE address_prefix = %s LIMIT 1)
+        e.parent_verification = (SELECT path_verification FROM observed_route WHERE address_prefix = %s AND NOT withdrawal LIMIT 1)
     ORDER BY
         e.recorded_time
     OFFSET
@@ -465,7 +481,7 @@ from
     exchange_report as e
 join
     observed_route as o
-on e.parent_observed_route = o.observed_route_id
+on e.parent_verification = o.path_verification
 where e.from_as = %s
 union
 select
@@ -478,7 +494,7 @@ from
     exchange_report as e
 join
     observed_route as o
-on e.parent_observed_route = o.observed_route_id
+on e.parent_verification = o.path_verification
 where e.to_as = %s
                        """,
         as_num,
@@ -0,0 +1,39 @@
+-- Migrate a database created with the `json` IR and report diff columns
+-- to `jsonb`, with GIN indexes on the IR, as `demo_v1.sql` now declares them.
+-- Run once: `psql -U postgres -d irv_server_test -f migrate_jsonb.sql`.
+-- Rewriting the tables takes a while on a full IR.
+begin;
+
+alter table aut_num
+	alter column imports type jsonb using imports::jsonb,
+	alter column exports type jsonb using exports::jsonb;
+alter table peering_set
+	alter column peerings type jsonb using peerings::jsonb;
+alter table filter_set
+	alter column filters type jsonb using filters::jsonb;
+alter table if exists policy_rule
+	alter column filter type jsonb using filter::jsonb;
+alter table if exists policy_peering
+	alter column remote_as type jsonb using remote_as::jsonb,
+	alter column remote_router type jsonb using remote_router::jsonb,
+	alter column local_router type jsonb using local_router::jsonb,
+	alter column actions type jsonb using actions::jsonb;
+alter table if exists report_diff
+	alter column added_items type jsonb using added_items::jsonb,
+	alter column removed_items type jsonb using removed_items::jsonb;
+
+create index if not exists aut_num_imports on aut_num using gin (imports jsonb_path_ops);
+create index if not exists aut_num_exports on aut_num using gin (exports jsonb_path_ops);
+create index if not exists peering_set_peerings on peering_set using gin (peerings jsonb_path_ops);
+create index if not exists filter_set_filters on filter_set using gin (filters jsonb_path_ops);
+do $$
+begin
+	if to_regclass('policy_rule') is not null then
+		create index if not exists policy_rule_filter on policy_rule using gin (filter jsonb_path_ops);
+	end if;
+	if to_regclass('policy_peering') is not null then
+		create index if not exists policy_peering_remote_as on policy_peering using gin (remote_as jsonb_path_ops);
+	end if;
+end $$;
+
+commit;
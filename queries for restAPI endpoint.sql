@@ -1,40 +1,33 @@
 -- Used.
--- Query ASes, routes, reports, and report items for a given overall type
--- the reports belong to with timestamps, item count, and paging.
+-- Paged queries walk reports in `(recorded_time, report_id)` order:
+-- first the keys of the next page of reports after the last one seen,
+-- then the rows of those reports.
+-- The cursor `(recorded_micros, report_id)` is the last key of the previous page;
+-- drop the condition on it for the first page.
+-- Reports listed with their observed routes are paged by
+-- `(recorded_time, report_id, observed_route_id)` instead,
+-- so many routes sharing a report cannot overflow a page.
+-- Query the next page of reports and routes for a given overall type.
 SELECT
-    e.from_as AS source_as,
-    e.to_as AS destination_as,
-    e.import,
-    e.overall_type,
-    e.recorded_time AS exchange_report_time,
-    r.observed_route_id,
-    r.raw_line,
-    r.address_prefix,
-    r.recorded_time AS observed_route_time,
-    ri.category AS report_category,
-    ri.specific_case AS report_specific_case,
-    ri.str_content AS report_string_content,
-    ri.num_content AS report_numeric_content,
-    COUNT(*) OVER () AS total_items
+    (extract(epoch FROM e.recorded_time) * 1000000)::bigint AS recorded_micros,
+    e.report_id,
+    r.observed_route_id
 FROM
     exchange_report e
 JOIN
-    observed_route r ON e.parent_observed_route = r.observed_route_id
-LEFT JOIN
-    report_item ri ON e.report_id = ri.parent_report
+    observed_route r ON e.parent_verification = r.path_verification
 WHERE
     e.overall_type = 'ok'
+    AND (e.recorded_time, e.report_id, r.observed_route_id) > ('epoch'::timestamp + 1687212000123456 * interval '1 microsecond', 42, 7)
 ORDER BY
-    e.recorded_time
-OFFSET
-    0
+    e.recorded_time, e.report_id, r.observed_route_id
 LIMIT
     10;
 
 -- Used.
---Query ASes, routes, reports and report items for a given specific case
---the report items belong to.
+-- Query ASes, routes, reports, and report items for the pairs of a page.
 SELECT
+    e.report_id,
     e.from_as AS source_as,
     e.to_as AS destination_as,
     e.import,
@@ -47,48 +40,58 @@ SELECT
     ri.category AS report_category,
     ri.specific_case AS report_specific_case,
     ri.str_content AS report_string_content,
-    ri.num_content AS report_numeric_content,
-    COUNT(*) OVER () AS total_items
+    ri.num_content AS report_numeric_content
 FROM
     exchange_report e
 JOIN
-    observed_route r ON e.parent_observed_route = r.observed_route_id
-JOIN
+    observed_route r ON e.parent_verification = r.path_verification
+LEFT JOIN
     report_item ri ON e.report_id = ri.parent_report
 WHERE
-    ri.specific_case = 'err_peering'
+    (e.report_id, r.observed_route_id) IN (SELECT * FROM unnest('{1, 1, 2}'::int[], '{7, 8, 7}'::int[]))
 ORDER BY
-    exchange_report_time
-OFFSET
-    0
-LIMIT
-    10;
+    e.recorded_time, e.report_id, r.observed_route_id, ri.report_item_id;
+
+-- Used.
+-- Count the reports for a given overall type up to 10000,
+-- beyond which `EXPLAIN (FORMAT JSON)` of the selection estimates the count.
+SELECT count(*) FROM (
+    SELECT 1 FROM exchange_report WHERE overall_type = 'ok' LIMIT 10000
+) capped;
 
 -- Used.
---Query reports and report items for a given Route object.
+-- Query the next page of reports and routes with report items of a given specific case.
+-- Their rows join `report_item` with `ri.specific_case = 'err_peering'`.
 SELECT
+    (extract(epoch FROM e.recorded_time) * 1000000)::bigint AS recorded_micros,
     e.report_id,
-    e.from_as AS source_as,
-    e.to_as AS destination_as,
-    e.import,
-    e.overall_type,
-    e.recorded_time AS exchange_report_time,
-    ri.report_item_id,
-    ri.category AS report_category,
-    ri.specific_case AS report_specific_case,
-    ri.str_content AS report_string_content,
-    ri.num_content AS report_numeric_content,
-    COUNT(*) OVER () AS total_items
+    r.observed_route_id
 FROM
     exchange_report e
 JOIN
-    report_item ri ON e.report_id = ri.parent_report
+    observed_route r ON e.parent_verification = r.path_verification
 WHERE
-    e.parent_observed_route = (SELECT observed_route_id FROM observed_route WHERE address_prefix = '1.0.0.0/24' LIMIT 1)
+    EXISTS (
+        SELECT 1 FROM report_item
+        WHERE parent_report = e.report_id AND specific_case = 'err_peering'
+    )
+    AND (e.recorded_time, e.report_id, r.observed_route_id) > ('epoch'::timestamp + 1687212000123456 * interval '1 microsecond', 42, 7)
 ORDER BY
-    e.recorded_time
-OFFSET
-    0
+    e.recorded_time, e.report_id, r.observed_route_id
+LIMIT
+    10;
+
+-- Used.
+-- Query the next page of reports for a given Route object.
+SELECT
+    (extract(epoch FROM recorded_time) * 1000000)::bigint AS recorded_micros,
+    report_id
+FROM
+    exchange_report
+WHERE
+    parent_verification = (SELECT path_verification FROM observed_route WHERE address_prefix = '1.0.0.0/24' AND NOT withdrawal LIMIT 1)
+ORDER BY
+    recorded_time, report_id
 LIMIT
     10;
 
@@ -100,7 +103,7 @@ SELECT
     exchange_report.to_as,
     exchange_report.import,
     exchange_report.overall_type,
-    exchange_report.parent_observed_route,
+    exchange_report.parent_verification,
     exchange_report.recorded_time,
     observed_route.raw_line,
     observed_route.address_prefix,
@@ -108,7 +111,7 @@ SELECT
 FROM
     exchange_report
 JOIN
-    observed_route ON exchange_report.parent_observed_route = observed_route.observed_route_id
+    observed_route ON exchange_report.parent_verification = observed_route.path_verification
 WHERE
     observed_route.observed_route_id = 10
 ORDER BY
@@ -119,21 +122,34 @@ LIMIT
     10;
 
 -- Used.
--- -- Reports for AutNum.
-
-SELECT * FROM (
-    SELECT report_id
-    FROM exchange_report
-    JOIN autonomous_system ON exchange_report.from_as = autonomous_system.as_num
-    WHERE as_num = %s
-    union
-    SELECT report_id
-    FROM exchange_report
-    JOIN autonomous_system ON exchange_report.to_as = autonomous_system.as_num
-    WHERE as_num = %s
-) AS as_and_report
-natural JOIN exchange_report
-ORDER BY report_id;
+-- Query the next page of reports from or to a given AutNum,
+-- merging the pages from each side so both walk their own index.
+SELECT
+    (extract(epoch FROM recorded_time) * 1000000)::bigint AS recorded_micros,
+    report_id
+FROM
+    exchange_report
+WHERE report_id IN (
+    (
+        SELECT report_id FROM exchange_report
+        WHERE from_as = 3356
+        AND (recorded_time, report_id) > ('epoch'::timestamp + 1687212000123456 * interval '1 microsecond', 42)
+        ORDER BY recorded_time, report_id
+        LIMIT 10
+    )
+    UNION
+    (
+        SELECT report_id FROM exchange_report
+        WHERE to_as = 3356
+        AND (recorded_time, report_id) > ('epoch'::timestamp + 1687212000123456 * interval '1 microsecond', 42)
+        ORDER BY recorded_time, report_id
+        LIMIT 10
+    )
+)
+ORDER BY
+    recorded_time, report_id
+LIMIT
+    10;
 
 -- Used.
 -- Routes for AutNum.
@@ -147,7 +163,7 @@ from
     exchange_report as e
 join
     observed_route as o
-on e.parent_observed_route = o.observed_route_id
+on e.parent_verification = o.path_verification
 where e.from_as = %s
 union
 select
@@ -160,5 +176,119 @@ from
     exchange_report as e
 join
     observed_route as o
-on e.parent_observed_route = o.observed_route_id
+on e.parent_verification = o.path_verification
 where e.to_as = %s
+
+-- Report chain of an observed route in AS path order,
+-- from the collector's peer towards the origin.
+SELECT
+    e.hop_index,
+    e.report_index,
+    e.from_as,
+    e.to_as,
+    e.import,
+    e.overall_type
+FROM
+    exchange_report e
+WHERE
+    e.parent_verification = (SELECT path_verification FROM observed_route WHERE observed_route_id = 10)
+ORDER BY
+    e.hop_index,
+    e.report_index DESC;
+
+-- When a route with bad reports was announced and next withdrawn by the same peer.
+SELECT
+    a.observed_route_id,
+    a.address_prefix,
+    a.peer_ip,
+    a.observed_time AS announced_time,
+    (
+        SELECT min(w.observed_time)
+        FROM observed_route w
+        WHERE w.withdrawal
+            AND w.address_prefix = a.address_prefix
+            AND w.peer_ip = a.peer_ip
+            AND w.observed_time > a.observed_time
+    ) AS withdrawn_time
+FROM
+    observed_route a
+JOIN
+    path_verification v ON a.path_verification = v.path_verification_id
+WHERE
+    NOT a.withdrawal
+    AND a.observed_time IS NOT NULL
+    AND v.n_bad > 0
+ORDER BY
+    a.observed_time;
+
+-- Count reports of each overall type per organization of the exporting AS,
+-- e.g., to triage bad reports between sibling ASes.
+SELECT
+    o.org_id,
+    o.org_name,
+    e.overall_type,
+    COUNT(*) AS n_report
+FROM
+    exchange_report e
+JOIN
+    as_organization a ON a.as_num = e.from_as
+JOIN
+    organization o ON o.org_id = a.org_id
+GROUP BY
+    o.org_id, o.org_name, e.overall_type
+ORDER BY
+    n_report DESC;
+
+-- Aut-nums with a rule importing from AS3356 directly.
+SELECT DISTINCT
+    r.as_num
+FROM
+    policy_rule r
+JOIN
+    policy_peering p ON p.policy_rule = r.policy_rule_id
+WHERE
+    r.import
+    AND p.remote_as @> '{"Single": {"Num": 3356}}';
+
+-- Aut-nums whose rules filter with exactly `fltr-bogons`.
+SELECT DISTINCT
+    as_num
+FROM
+    policy_rule
+WHERE
+    filter @> '{"FilterSet": "fltr-bogons"}';
+
+-- Objects whose policies reference as-set `AS-FOO`, with the referencing rule.
+SELECT
+    r.rpsl_obj_name,
+    p.import,
+    p.rule_index,
+    r.in_peering
+FROM
+    policy_reference r
+LEFT JOIN
+    policy_rule p ON p.policy_rule_id = r.policy_rule
+WHERE
+    r.referenced_type = 'as_set'
+    AND r.referenced_name = 'AS-FOO';
+
+-- Objects referenced but never defined, most referenced first.
+SELECT
+    referenced_type,
+    referenced_name,
+    COUNT(*) AS n_reference
+FROM
+    undefined_reference
+GROUP BY
+    referenced_type, referenced_name
+ORDER BY
+    n_reference DESC;
+
+-- Filter-sets whose filters include route-set `RS-BOGONS` at the top level,
+-- served by the GIN index on `filter_set.filters`.
+SELECT
+    filter_set_name
+FROM
+    filter_set
+WHERE
+    filters @> '[{"RouteSet": ["RS-BOGONS"]}]';
@@ -0,0 +1,158 @@
+"""Generate the small MRT files the `native_mrt` tests read.
+
+Run from this directory: `python3 make_mrt.py`.
+"""
+
+import bz2
+import ipaddress
+import struct
+
+
+def mrt(timestamp, mrt_type, subtype, body):
+    return struct.pack("!IHHI", timestamp, mrt_type, subtype, len(body)) + body
+
+
+def ip(addr):
+    return ipaddress.ip_address(addr).packed
+
+
+def prefix(network):
+    network = ipaddress.ip_network(network)
+    n_byte = (network.prefixlen + 7) // 8
+    return bytes([network.prefixlen]) + network.network_address.packed[:n_byte]
+
+
+def attribute(attribute_type, value, flags=0x40):
+    if len(value) > 255:
+        return struct.pack("!BBH", flags | 0x10, attribute_type, len(value)) + value
+    return struct.pack("!BBB", flags, attribute_type, len(value)) + value
+
+
+def origin(value):
+    return attribute(1, bytes([value]))
+
+
+def as_path(segments, as4=True, attribute_type=2):
+    fmt = "!I" if as4 else "!H"
+    value = b""
+    for segment_type, nums in segments:
+        value += bytes([segment_type, len(nums)])
+        value += b"".join(struct.pack(fmt, num) for num in nums)
+    return attribute(attribute_type, value)
+
+
+def next_hop(addr):
+    return attribute(3, ip(addr))
+
+
+def communities(values):
+    return attribute(8, b"".join(struct.pack("!I", value) for value in values), 0xC0)
+
+
+def rib_entry(peer_index, attributes):
+    return struct.pack("!HIH", peer_index, 1687212000, len(attributes)) + attributes
+
+
+def bgp_update(withdrawn, attributes, nlri):
+    withdrawn = b"".join(prefix(network) for network in withdrawn)
+    body = struct.pack("!H", len(withdrawn)) + withdrawn
+    body += struct.pack("!H", len(attributes)) + attributes
+    body += b"".join(prefix(network) for network in nlri)
+    return b"\xff" * 16 + struct.pack("!HB", 19 + len(body), 2) + body
+
+
+def bgp4mp(peer_as, peer_ip, message, as4=True):
+    fmt = "!IIHH" if as4 else "!HHHH"
+    afi = 2 if ":" in peer_ip else 1
+    local_ip = "2001:db8::fe" if afi == 2 else "192.0.2.254"
+    return struct.pack(fmt, peer_as, 65000, 0, afi) + ip(peer_ip) + ip(local_ip) + message
+
+
+def rib():
+    peers = struct.pack("!BI", 0x02, 0x0A000001) + ip("192.0.2.1") + struct.pack("!I", 64496)
+    peers += struct.pack("!BI", 0x03, 0x0A000002) + ip("2001:db8::1")
+    peers += struct.pack("!I", 4200000001)
+    peer_index = ip("10.0.0.1") + struct.pack("!H", 0) + struct.pack("!H", 2) + peers
+
+    v4_entries = rib_entry(
+        0,
+        origin(0)
+        + as_path([(2, [64496, 3356, 3356, 13335])])
+        + next_hop("192.0.2.1")
+        + attribute(4, struct.pack("!I", 10), 0x80)
+        + communities([3356 << 16 | 100, 0xFFFFFF01]),
+    )
+    v4_entries += rib_entry(
+        1,
+        origin(2)
+        + as_path([(2, [4200000001, 174]), (1, [65001, 65002])])
+        + next_hop("192.0.2.2")
+        + attribute(5, struct.pack("!I", 100))
+        + attribute(6, b"")
+        + attribute(7, struct.pack("!I", 174) + ip("198.51.100.1"), 0xC0)
+        + attribute(32, struct.pack("!III", 4200000001, 1, 2), 0xC0),
+    )
+    v4 = struct.pack("!I", 0) + prefix("203.0.113.0/24") + struct.pack("!H", 2) + v4_entries
+
+    # RIB entries abbreviate MP_REACH_NLRI to the next hop.
+    mp_reach = attribute(14, bytes([16]) + ip("2001:db8::1"), 0x80)
+    v6_entries = rib_entry(1, origin(0) + as_path([(2, [4200000001, 6939])]) + mp_reach)
+    v6 = struct.pack("!I", 1) + prefix("2001:db8:1000::/36") + struct.pack("!H", 1) + v6_entries
+
+    return (
+        mrt(1687212000, 13, 1, peer_index)
+        + mrt(1687212000, 13, 2, v4)
+        + mrt(1687212000, 13, 4, v6)
+    )
+
+
+def updates():
+    as4_update = bgp_update(
+        ["198.51.100.0/24"],
+        origin(0) + as_path([(2, [64496, 13335])]) + next_hop("192.0.2.1"),
+        ["203.0.113.0/24", "203.0.113.128/25"],
+    )
+
+    # A two-byte speaker passing on four-byte AS numbers in AS4_PATH.
+    as2_update = bgp_update(
+        [],
+        origin(1)
+        + as_path([(2, [64497, 23456, 23456])], as4=False)
+        + next_hop("192.0.2.5")
+        + attribute(7, struct.pack("!H", 23456) + ip("10.0.0.9"), 0xC0)
+        + as_path([(2, [4200000002, 4200000003])], attribute_type=17)
+        + attribute(18, struct.pack("!I", 4200000003) + ip("10.0.0.9"), 0xC0),
+        ["192.0.2.0/24"],
+    )
+
+    mp_unreach = attribute(15, struct.pack("!HB", 2, 1) + prefix("2001:db8:dead::/48"), 0x80)
+    mp_reach = attribute(
+        14,
+        struct.pack("!HBB", 2, 1, 32)
+        + ip("2001:db8::1")
+        + ip("fe80::1")
+        + b"\x00"
+        + prefix("2001:db8:beef::/48"),
+        0x80,
+    )
+    v6_update = bgp_update(
+        [], origin(0) + as_path([(2, [4200000001, 6939])]) + mp_unreach + mp_reach, []
+    )
+
+    state_change = bgp4mp(64496, "192.0.2.1", struct.pack("!HH", 1, 6))
+    keepalive = b"\xff" * 16 + struct.pack("!HB", 19, 4)
+
+    return (
+        mrt(1687212000, 16, 4, bgp4mp(64496, "192.0.2.1", as4_update))
+        + mrt(1687212001, 16, 1, bgp4mp(64497, "192.0.2.5", as2_update, as4=False))
+        + mrt(1687212002, 17, 4, struct.pack("!I", 123) + bgp4mp(4200000001, "2001:db8::1", v6_update))
+        + mrt(1687212003, 16, 5, state_change)
+        + mrt(1687212004, 16, 4, bgp4mp(64496, "192.0.2.1", keepalive))
+    )
+
+
+if __name__ == "__main__":
+    with open("rib.mrt.bz2", "wb") as file:
+        file.write(bz2.compress(rib()))
+    with open("updates.mrt", "wb") as file:
+        file.write(updates())
@@ -0,0 +1,42 @@
+-- Small recorded run for tests of the queries behind the server.
+-- AS4200000000 is stored wrapped into `int` as -94967296.
+-- Reports share `recorded_time` so paging has to break ties by `report_id`,
+-- and routes 1 to 3 share a path so a report is listed with several routes.
+insert into rpsl_obj (rpsl_obj_name, body) values
+	('AS3356', 'aut-num: AS3356'),
+	('AS4200000000', 'aut-num: AS4200000000');
+insert into aut_num (as_num, as_name, imports, exports, rpsl_obj_name) values
+	(3356, 'LEVEL3', '{}', '{}', 'AS3356'),
+	(-94967296, 'EXAMPLE-32BIT', '{}', '{}', 'AS4200000000');
+insert into verification_run
+	(verbosity, ir_dir_hash, as_rel_file, as_rel_hash, input_file, verifier_version, loader_version)
+values ('{}', 'ir', 'as-rel', 'as-rel', 'rib', 'test', 'test');
+insert into route_path (content_hash, address_prefix, as_path) values
+	('path-1', '10.0.0.0/8', '3356 4200000000'),
+	('path-2', '192.0.2.0/24', '64496 3356');
+insert into path_verification (
+	route_path_id, ir_dir_hash, as_rel_hash, verifier_version, verbosity, verification_run,
+	worst_type, n_ok, n_skip, n_unrecorded, n_special_case, n_bad, fully_verified
+) values
+	(1, 'ir', 'as-rel', 'test', '{}', 1, 'bad', 1, 0, 0, 0, 3, false),
+	(2, 'ir', 'as-rel', 'test', '{}', 1, 'bad', 0, 0, 0, 0, 2, false);
+insert into observed_route (raw_line, address_prefix, path_verification, verification_run, input_index) values
+	('10.0.0.0/8|3356 4200000000', '10.0.0.0/8', 1, 1, 0),
+	('10.0.0.0/8|3356 4200000000', '10.0.0.0/8', 1, 1, 1),
+	('10.0.0.0/8|3356 4200000000', '10.0.0.0/8', 1, 1, 2),
+	('192.0.2.0/24|64496 3356', '192.0.2.0/24', 2, 1, 3);
+insert into exchange_report
+	(from_as, to_as, import, overall_type, parent_verification, verification_run, report_index, hop_index, recorded_time)
+values
+	(3356, -94967296, true, 'bad', 1, 1, 0, 0, '2023-07-01 00:00:01'),
+	(-94967296, 3356, false, 'ok', 1, 1, 1, 0, '2023-07-01 00:00:00'),
+	(64496, 3356, true, 'bad', 2, 1, 0, 0, '2023-07-01 00:00:00'),
+	(3356, 64496, false, 'bad', 2, 1, 1, 0, '2023-07-01 00:00:01'),
+	(3356, 174, true, 'bad', 1, 1, 2, 1, '2023-07-01 00:00:00'),
+	(174, 3356, false, 'bad', 1, 1, 3, 1, '2023-07-01 00:00:01');
+insert into report_item (category, specific_case, str_content, num_content, parent_report) values
+	('bad', 'err_filter', null, 3356, 1),
+	('bad', 'err_filter', null, 64496, 3),
+	('bad', 'err_peering', 'AS64496', null, 3),
+	('bad', 'err_filter', null, 3356, 5),
+	('bad', 'err_peering', null, 174, 6);
@@ -0,0 +1,15 @@
+//! Boundary to the upstream `route_verification` crate.
+//!
+//! Each supported upstream major version has its own module, selected by a
+//! `route_verification_v*` cargo feature.
+//! A module re-exports the upstream items the loader needs and converts
+//! upstream reports into [`crate::report`],
+//! so the persistence code never matches upstream report shapes.
+
+#[cfg(feature = "route_verification_v0_2")]
+mod v0_2;
+#[cfg(feature = "route_verification_v0_2")]
+pub use v0_2::*;
+
+#[cfg(not(any(feature = "route_verification_v0_2")))]
+compile_error!("Enable a `route_verification_v*` feature to pick the upstream version.");
@@ -0,0 +1,945 @@
+//! Adapter for `route_verification` 0.2.
+use std::collections::BTreeSet;
+#[cfg(feature = "native_mrt")]
+use std::io::Read;
+use std::path::Path;
+#[cfg(not(feature = "native_mrt"))]
+use std::{io::BufRead, mem};
+
+use anyhow::{ensure, Result};
+use log::warn;
+#[cfg(not(feature = "native_mrt"))]
+use route_verification_v0_2::{bgp::wrapper::read_mrt, io::cmd::OutputChild};
+use route_verification_v0_2::{
+    bgp::{AsPathEntry, Compare, Report, ReportItem},
+    ir::{AsExpr, AsName, Casts},
+};
+use sqlx::types::ipnetwork::IpNetwork;
+
+#[cfg(feature = "native_mrt")]
+use crate::mrt::{open_mrt, MrtEntry, MrtReader};
+use crate::{
+    policy::{PolicyPeering, PolicyReference, PolicyRule},
+    report::{
+        ExchangeReport, ObservedRoute, ObservedUpdate, ReportItemRecord, RoutePath, VerifiedRoute,
+    },
+    report_type::{OverallReportType, ReportItemType},
+    run::InputEntries,
+    text::{read_text_routes, TextRoute, TextRoutes},
+};
+
+pub use route_verification_v0_2::{
+    as_rel::{AsRelDb, Relationship},
+    bgp::{Line, QueryIr, Verbosity},
+    ir::{
+        AddrPfxRange, AsSet, AutNum, Filter, FilterSet, Ir, Peering, PeeringSet, RouteSet,
+        RouteSetMember, Versions,
+    },
+    lex::{expressions, io_wrapper_lines, lines_continued, rpsl_objects, RpslExpr},
+};
+
+/// Upstream verifier recorded with each verification run.
+pub const VERIFIER_VERSION: &str = "route_verification 0.2";
+
+/// Verbosity [`verify`] uses.
+const VERBOSITY: Verbosity = Verbosity::all_stats();
+
+/// Names of the [`VERBOSITY`] flags turned on.
+pub fn verbosity_flags() -> Vec<&'static str> {
+    let Verbosity {
+        stop_at_first,
+        show_meh,
+        show_unrec,
+        show_skips,
+        show_success,
+        per_peering_err,
+        per_filter_err,
+        all_err,
+        record_set,
+        record_community,
+        special_uphill,
+        check_customer,
+        check_import_only_provider,
+    } = VERBOSITY;
+    [
+        (stop_at_first, "stop_at_first"),
+        (show_meh, "show_meh"),
+        (show_unrec, "show_unrec"),
+        (show_skips, "show_skips"),
+        (show_success, "show_success"),
+        (per_peering_err, "per_peering_err"),
+        (per_filter_err, "per_filter_err"),
+        (all_err, "all_err"),
+        (record_set, "record_set"),
+        (record_community, "record_community"),
+        (special_uphill, "special_uphill"),
+        (check_customer, "check_customer"),
+        (check_import_only_provider, "check_import_only_provider"),
+    ]
+    .into_iter()
+    .filter_map(|(is_true, tag)| is_true.then_some(tag))
+    .collect()
+}
+
+/// Lines of the MRT file at `path`, decoded by `bgpdump` one at a time.
+/// `bgpdump` output cannot seek, so `_offset` is ignored.
+#[cfg(not(feature = "native_mrt"))]
+pub fn stream_mrt<P: AsRef<Path>>(path: P, _offset: Option<u64>) -> Result<MrtLines> {
+    Ok(MrtLines {
+        bgpdump: read_mrt(path)?,
+        buffer: String::new(),
+    })
+}
+
+/// Iterator from [`stream_mrt`].
+/// Dropping it kills `bgpdump`.
+#[cfg(not(feature = "native_mrt"))]
+pub struct MrtLines {
+    bgpdump: OutputChild,
+    buffer: String,
+}
+
+#[cfg(not(feature = "native_mrt"))]
+impl Iterator for MrtLines {
+    type Item = Result<Line>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.bgpdump.stdout.read_line(&mut self.buffer) {
+            Ok(0) => None,
+            Ok(_) => Some(Line::from_raw(mem::take(&mut self.buffer))),
+            Err(why) => Some(Err(why.into())),
+        }
+    }
+}
+
+#[cfg(not(feature = "native_mrt"))]
+impl InputEntries for MrtLines {
+    fn offset(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Announcements in the MRT file at `path`, decoded in process one record
+/// at a time, starting from the record at byte `offset` if given;
+/// see [`open_mrt`].
+/// Withdrawals are skipped.
+#[cfg(feature = "native_mrt")]
+pub fn stream_mrt<P: AsRef<Path>>(path: P, offset: Option<u64>) -> Result<MrtLines> {
+    Ok(MrtLines {
+        entries: open_mrt(path, offset)?,
+    })
+}
+
+/// Iterator from [`stream_mrt`].
+#[cfg(feature = "native_mrt")]
+pub struct MrtLines {
+    entries: MrtReader<Box<dyn Read + Send>>,
+}
+
+#[cfg(feature = "native_mrt")]
+impl Iterator for MrtLines {
+    type Item = Result<Line>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.entries.next()? {
+                Ok(entry) => {
+                    if let Some(line) = mrt_line(entry) {
+                        return Some(line);
+                    }
+                }
+                Err(why) => return Some(Err(why)),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "native_mrt")]
+impl InputEntries for MrtLines {
+    fn offset(&self) -> Option<u64> {
+        self.entries.offset()
+    }
+}
+
+/// Build the [`Line`] `bgpdump` would have produced for `entry`.
+/// `None` for withdrawals, which carry no AS path to verify.
+#[cfg(feature = "native_mrt")]
+pub fn mrt_line(entry: MrtEntry) -> Option<Result<Line>> {
+    let as_path = entry.as_path?;
+    let line = || {
+        let compare = compare(&entry.prefix.to_string(), &as_path)?;
+        Ok(Line::new(entry.raw, compare, None))
+    };
+    Some(line())
+}
+
+/// Parse the prefix and AS path the way [`Line::from_raw`] does.
+fn compare(prefix: &str, as_path: &str) -> Result<Compare> {
+    let as_path = as_path
+        .split(' ')
+        .map(str::parse)
+        .collect::<Result<Vec<AsPathEntry>>>()?;
+    Ok(Compare::new(prefix.parse()?, as_path))
+}
+
+/// [`Line`]s for the routes in the text export at `path`,
+/// starting from the line at byte `offset` if given;
+/// see [`crate::text`].
+pub fn stream_text_routes<P: AsRef<Path>>(path: P, offset: Option<u64>) -> Result<TextLines> {
+    Ok(TextLines {
+        routes: read_text_routes(path, offset)?,
+    })
+}
+
+/// Iterator from [`stream_text_routes`].
+pub struct TextLines {
+    routes: TextRoutes,
+}
+
+impl Iterator for TextLines {
+    type Item = Result<Line>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = |route: Result<TextRoute>| {
+            let route = route?;
+            let compare = compare(&route.prefix, &route.as_path)?;
+            Ok(Line::new(route.raw, compare, None))
+        };
+        Some(line(self.routes.next()?))
+    }
+}
+
+impl InputEntries for TextLines {
+    fn offset(&self) -> Option<u64> {
+        Some(self.routes.offset())
+    }
+}
+
+/// Rebuild the [`Line`] of a route stored in the database.
+pub fn stored_line(raw_line: String, path: &RoutePath) -> Result<Line> {
+    let compare = compare(&path.address_prefix.to_string(), &path.as_path)?;
+    Ok(Line::new(raw_line, compare, None))
+}
+
+/// Entry of a BGP update dump.
+#[derive(Clone, Debug)]
+pub struct UpdateLine {
+    pub update: ObservedUpdate,
+    /// The announced route. `None` for withdrawals.
+    pub announcement: Option<Line>,
+}
+
+/// Updates in the BGP4MP dump at `path`, one at a time,
+/// starting from the record at byte `offset` if given and seekable,
+/// like [`stream_mrt`].
+/// Records other than announcements and withdrawals, e.g., state changes,
+/// are skipped.
+pub fn stream_updates<P: AsRef<Path>>(path: P, offset: Option<u64>) -> Result<UpdateLines> {
+    #[cfg(not(feature = "native_mrt"))]
+    let _ = offset; // `bgpdump` output cannot seek.
+    Ok(UpdateLines {
+        #[cfg(not(feature = "native_mrt"))]
+        bgpdump: read_mrt(path)?,
+        #[cfg(not(feature = "native_mrt"))]
+        buffer: String::new(),
+        #[cfg(feature = "native_mrt")]
+        entries: open_mrt(path, offset)?,
+    })
+}
+
+/// Iterator from [`stream_updates`].
+pub struct UpdateLines {
+    #[cfg(not(feature = "native_mrt"))]
+    bgpdump: OutputChild,
+    #[cfg(not(feature = "native_mrt"))]
+    buffer: String,
+    #[cfg(feature = "native_mrt")]
+    entries: MrtReader<Box<dyn Read + Send>>,
+}
+
+impl UpdateLines {
+    /// Next line as `bgpdump -m` prints it.
+    #[cfg(not(feature = "native_mrt"))]
+    fn next_raw(&mut self) -> Option<Result<String>> {
+        match self.bgpdump.stdout.read_line(&mut self.buffer) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(mem::take(&mut self.buffer))),
+            Err(why) => Some(Err(why.into())),
+        }
+    }
+
+    /// Next line as `bgpdump -m` prints it.
+    #[cfg(feature = "native_mrt")]
+    fn next_raw(&mut self) -> Option<Result<String>> {
+        Some(self.entries.next()?.map(|entry| entry.raw))
+    }
+}
+
+impl Iterator for UpdateLines {
+    type Item = Result<UpdateLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.next_raw()?.and_then(parse_update) {
+                Ok(Some(update)) => return Some(Ok(update)),
+                Ok(None) => continue,
+                Err(why) => return Some(Err(why)),
+            }
+        }
+    }
+}
+
+impl InputEntries for UpdateLines {
+    #[cfg(not(feature = "native_mrt"))]
+    fn offset(&self) -> Option<u64> {
+        None
+    }
+
+    #[cfg(feature = "native_mrt")]
+    fn offset(&self) -> Option<u64> {
+        self.entries.offset()
+    }
+}
+
+/// Parse a BGP4MP line `bgpdump -m` prints, e.g.,
+/// `BGP4MP|1687212000|A|192.0.2.1|64496|203.0.113.0/24|64496 13335|IGP|…`
+/// or `BGP4MP|1687212000|W|192.0.2.1|64496|198.51.100.0/24`.
+/// `None` for lines that are neither announcements nor withdrawals.
+pub fn parse_update(raw: String) -> Result<Option<UpdateLine>> {
+    let fields: Vec<_> = raw.trim_end().split('|').collect();
+    ensure!(
+        fields[0].starts_with("BGP4MP") && fields.len() >= 3,
+        "`{}` is not a BGP4MP line",
+        raw.trim_end()
+    );
+    let withdrawal = match fields[2] {
+        "A" => false,
+        "W" => true,
+        _ => return Ok(None),
+    };
+    ensure!(
+        fields.len() >= if withdrawal { 6 } else { 7 },
+        "`{}` has too few fields",
+        raw.trim_end()
+    );
+    let (timestamp, microseconds) = match fields[1].split_once('.') {
+        Some((timestamp, microseconds)) => (timestamp.parse()?, microseconds.parse()?),
+        None => (fields[1].parse()?, 0),
+    };
+    let announcement = match withdrawal {
+        true => None,
+        false => Some(Line::new(raw.clone(), compare(fields[5], fields[6])?, None)),
+    };
+    let update = ObservedUpdate {
+        address_prefix: fields[5].parse()?,
+        timestamp,
+        microseconds,
+        peer_ip: fields[3].parse()?,
+        peer_as: fields[4].parse()?,
+        withdrawal,
+        raw_line: raw,
+    };
+    Ok(Some(UpdateLine {
+        update,
+        announcement,
+    }))
+}
+
+/// Read the route in `line`.
+pub fn observed_route(line: &Line) -> ObservedRoute {
+    let prefix = line.compare.prefix;
+    let address_prefix = IpNetwork::new(prefix.addr(), prefix.prefix_len())
+        .expect("IpNet should be valid IpNetWork");
+    let as_path = line
+        .compare
+        .as_path
+        .iter()
+        .map(|entry| match entry {
+            AsPathEntry::Seq(num) => num.to_string(),
+            AsPathEntry::Set(nums) => {
+                let nums: Vec<_> = nums.iter().map(u32::to_string).collect();
+                format!("{{{}}}", nums.join(","))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    ObservedRoute {
+        raw_line: line.raw.clone(),
+        path: RoutePath {
+            address_prefix,
+            as_path,
+        },
+    }
+}
+
+/// Verify `line` against `query` with [`VERBOSITY`].
+pub fn verify(line: &mut Line, query: &QueryIr) -> VerifiedRoute {
+    line.compare.verbosity = VERBOSITY;
+    line.check(query);
+    verified_route(line)
+}
+
+/// Convert the reports stored in `line`.
+pub fn verified_route(line: &Line) -> VerifiedRoute {
+    let as_path = &line.compare.as_path;
+    let mut reports = Vec::new();
+    // Reports go from the origin towards the collector, so each hop is at or
+    // left of the previous one.
+    let mut last_hop = as_path.len().saturating_sub(1);
+    for (report_index, report) in line.report.iter().flatten().enumerate() {
+        let (from, to) = report_pair(report);
+        let hop_index = locate_hop(as_path, &from, &to, last_hop);
+        match hop_index {
+            Some(hop_index) => last_hop = hop_index,
+            None => warn!("Failed to locate {report:?} in {as_path:?}."),
+        }
+        if let Some(report) = exchange_report(report, report_index, hop_index) {
+            reports.push(report);
+        }
+    }
+    let set_hops = as_path
+        .windows(2)
+        .filter(|pair| {
+            pair.iter()
+                .any(|entry| matches!(entry, AsPathEntry::Set(_)))
+        })
+        .count();
+    VerifiedRoute {
+        set_hops: set_hops as u32,
+        reports,
+    }
+}
+
+/// Index in `as_path` of `from` exporting to `to`, at or before `last_hop`.
+fn locate_hop(
+    as_path: &[AsPathEntry],
+    from: &AsPathEntry,
+    to: &AsPathEntry,
+    last_hop: usize,
+) -> Option<usize> {
+    (1..=last_hop)
+        .rev()
+        .find(|&index| as_path[index] == *from && as_path[index - 1] == *to)
+}
+
+fn report_pair(report: &Report) -> (AsPathEntry, AsPathEntry) {
+    match report {
+        Report::OkImport { from, to }
+        | Report::OkExport { from, to }
+        | Report::SkipImport { from, to, .. }
+        | Report::SkipExport { from, to, .. }
+        | Report::UnrecImport { from, to, .. }
+        | Report::UnrecExport { from, to, .. }
+        | Report::MehImport { from, to, .. }
+        | Report::MehExport { from, to, .. }
+        | Report::BadImport { from, to, .. }
+        | Report::BadExport { from, to, .. } => (AsPathEntry::Seq(*from), AsPathEntry::Seq(*to)),
+        Report::AsPathPairWithSet { from, to } => (from.clone(), to.clone()),
+    }
+}
+
+fn exchange_report(
+    report: &Report,
+    report_index: usize,
+    hop_index: Option<usize>,
+) -> Option<ExchangeReport> {
+    let (from, to, import, overall_type, items) = match report {
+        Report::OkImport { from, to } => (from, to, true, OverallReportType::Ok, None),
+        Report::OkExport { from, to } => (from, to, false, OverallReportType::Ok, None),
+        Report::SkipImport { from, to, items } => {
+            (from, to, true, OverallReportType::Skip, Some(items))
+        }
+        Report::SkipExport { from, to, items } => {
+            (from, to, false, OverallReportType::Skip, Some(items))
+        }
+        Report::UnrecImport { from, to, items } => {
+            (from, to, true, OverallReportType::Unrecorded, Some(items))
+        }
+        Report::UnrecExport { from, to, items } => {
+            (from, to, false, OverallReportType::Unrecorded, Some(items))
+        }
+        Report::MehImport { from, to, items } => {
+            (from, to, true, OverallReportType::SpecialCase, Some(items))
+        }
+        Report::MehExport { from, to, items } => {
+            (from, to, false, OverallReportType::SpecialCase, Some(items))
+        }
+        Report::BadImport { from, to, items } => {
+            (from, to, true, OverallReportType::Bad, Some(items))
+        }
+        Report::BadExport { from, to, items } => {
+            (from, to, false, OverallReportType::Bad, Some(items))
+        }
+        report @ Report::AsPathPairWithSet { from: _, to: _ } => {
+            warn!("Encountered {:?}", report);
+            return None;
+        }
+    };
+    let items = items
+        .map(|items| items.iter().map(report_item).collect())
+        .unwrap_or_default();
+    Some(ExchangeReport {
+        from_as: *from,
+        to_as: *to,
+        import,
+        overall_type,
+        report_index: report_index as u32,
+        hop_index: hop_index.map(|index| index as u32),
+        items,
+    })
+}
+
+fn report_item(item: &ReportItem) -> ReportItemRecord {
+    let (specific_case, str_content, num_content) = report_item_parts(item);
+    ReportItemRecord {
+        specific_case,
+        str_content: str_content.cloned(),
+        num_content,
+    }
+}
+
+/// Map `item` to its database label and content.
+/// The match is exhaustive on purpose:
+/// a new upstream variant should fail compilation here.
+fn report_item_parts(item: &ReportItem) -> (ReportItemType, Option<&String>, Option<i32>) {
+    match item {
+        ReportItem::SkipAsRegexWithTilde(s) => (ReportItemType::SkipRegexTilde, Some(s), None),
+        ReportItem::SkipAsRegexPathWithSet => (ReportItemType::SkipRegexWithSet, None, None),
+        ReportItem::SkipCommunityCheckUnimplemented(_) => {
+            (ReportItemType::SkipCommunity, None, None)
+        }
+        ReportItem::UnrecordedAutNum(num) => (ReportItemType::UnrecAutNum, None, Some(*num as i32)),
+        ReportItem::UnrecImportEmpty => (ReportItemType::UnrecImportEmpty, None, None),
+        ReportItem::UnrecExportEmpty => (ReportItemType::UnrecExportEmpty, None, None),
+        ReportItem::UnrecordedAsSet(s) => (ReportItemType::UnrecAsSet, Some(s), None),
+        ReportItem::UnrecordedAsRoutes(num) => {
+            (ReportItemType::UnrecAsRoutes, None, Some(*num as i32))
+        }
+        ReportItem::UnrecordedAsSetRoute(s) => (ReportItemType::UnrecAsSetRoute, Some(s), None),
+        ReportItem::UnrecordedSomeAsSetRoute(s) => {
+            (ReportItemType::UnrecSomeAsSetRoute, Some(s), None)
+        }
+        ReportItem::UnrecordedRouteSet(s) => (ReportItemType::UnrecRouteSet, Some(s), None),
+        ReportItem::UnrecordedPeeringSet(s) => (ReportItemType::UnrecPeeringSet, Some(s), None),
+        ReportItem::UnrecordedFilterSet(s) => (ReportItemType::UnrecFilterSet, Some(s), None),
+        ReportItem::SpecAsIsOriginButNoRoute(num) => (
+            ReportItemType::SpecAsIsOriginButNoRoute,
+            None,
+            Some(*num as i32),
+        ),
+        ReportItem::SpecAsSetContainsOriginButNoRoute(s, num) => (
+            ReportItemType::SpecAsSetContainsOriginButNoRoute,
+            Some(s),
+            Some(*num as i32),
+        ),
+        ReportItem::SpecExportCustomers => (ReportItemType::SpecExportCustomers, None, None),
+        ReportItem::SpecImportFromNeighbor => (ReportItemType::SpecImportFromNeighbor, None, None),
+        ReportItem::SpecTier1Pair => (ReportItemType::SpecTier1Pair, None, None),
+        ReportItem::SpecImportPeerOIFPS => (ReportItemType::SpecImportPeerOIFPS, None, None),
+        ReportItem::SpecImportCustomerOIFPS => {
+            (ReportItemType::SpecImportCustomerOIFPS, None, None)
+        }
+        ReportItem::SpecUphillTier1 => (ReportItemType::SpecUphillTier1, None, None),
+        ReportItem::SpecUphill => (ReportItemType::SpecUphill, None, None),
+        ReportItem::MatchFilter => (ReportItemType::ErrFilter, None, None),
+        ReportItem::MatchFilterAsNum(num, _) => {
+            (ReportItemType::ErrFilterAsNum, None, Some(*num as i32))
+        }
+        ReportItem::MatchFilterAsSet(s, _) => (ReportItemType::ErrFilterAsSet, Some(s), None),
+        ReportItem::MatchFilterPrefixes => (ReportItemType::ErrFilterPrefixes, None, None),
+        ReportItem::MatchFilterRouteSet(s) => (ReportItemType::ErrFilterRouteSet, Some(s), None),
+        ReportItem::MatchRemoteAsNum(num) => {
+            (ReportItemType::ErrRemoteAsNum, None, Some(*num as i32))
+        }
+        ReportItem::MatchRemoteAsSet(s) => (ReportItemType::ErrRemoteAsSet, Some(s), None),
+        ReportItem::MatchExceptPeeringRight => (ReportItemType::ErrExceptPeeringRight, None, None),
+        ReportItem::MatchPeering => (ReportItemType::ErrPeering, None, None),
+        ReportItem::MatchRegex(s) => (ReportItemType::ErrRegex, Some(s), None),
+        ReportItem::RpslInvalidAsName(s) => (ReportItemType::RpslAsName, Some(s), None),
+        ReportItem::RpslInvalidFilter(s) => (ReportItemType::RpslFilter, Some(s), None),
+        ReportItem::RpslInvalidAsRegex(s) => (ReportItemType::RpslRegex, Some(s), None),
+        ReportItem::RpslUnknownFilter(s) => (ReportItemType::RpslUnknownFilter, Some(s), None),
+        ReportItem::RecCheckFilter => (ReportItemType::RecCheckFilter, None, None),
+        ReportItem::RecFilterRouteSet(s) => (ReportItemType::RecFilterRouteSet, Some(s), None),
+        ReportItem::RecFilterRouteSetMember(_) => {
+            (ReportItemType::RecFilterRouteSetMember, None, None)
+        }
+        ReportItem::RecFilterAsSet(s) => (ReportItemType::RecFilterAsSet, Some(s), None),
+        ReportItem::RecFilterAsName(_) => (ReportItemType::RecFilterAsName, None, None),
+        ReportItem::RecFilterAnd => (ReportItemType::RecFilterAnd, None, None),
+        ReportItem::RecFilterOr => (ReportItemType::RecFilterOr, None, None),
+        ReportItem::RecFilterNot => (ReportItemType::RecFilterNot, None, None),
+        ReportItem::RecCheckSetMember(s) => (ReportItemType::RecCheckSetMember, Some(s), None),
+        ReportItem::RecCheckRemoteAs => (ReportItemType::RecCheckRemoteAs, None, None),
+        ReportItem::RecRemoteAsName(_) => (ReportItemType::RecRemoteAsName, None, None),
+        ReportItem::RecRemoteAsSet(s) => (ReportItemType::RecRemoteAsSet, Some(s), None),
+        ReportItem::RecRemotePeeringSet(s) => (ReportItemType::RecRemotePeeringSet, Some(s), None),
+        ReportItem::RecPeeringAnd => (ReportItemType::RecPeeringAnd, None, None),
+        ReportItem::RecPeeringOr => (ReportItemType::RecPeeringOr, None, None),
+        ReportItem::RecPeeringExcept => (ReportItemType::RecPeeringExcept, None, None),
+    }
+}
+
+/// Flatten the import or export policy `versions` of an aut-num into rules,
+/// in the order of [`Versions::entries_iter`].
+pub fn policy_rules(versions: &Versions) -> Result<Vec<PolicyRule>> {
+    let mut rules = Vec::with_capacity(versions.len());
+    for (address_family, casts) in [
+        ("any", &versions.any),
+        ("ipv4", &versions.ipv4),
+        ("ipv6", &versions.ipv6),
+    ] {
+        let Casts {
+            any,
+            unicast,
+            multicast,
+        } = casts;
+        for (cast_type, entries) in [("any", any), ("unicast", unicast), ("multicast", multicast)] {
+            for entry in entries {
+                let mut references = BTreeSet::new();
+                for peering_action in &entry.mp_peerings {
+                    as_expr_references(&peering_action.mp_peering.remote_as, &mut references);
+                }
+                filter_references(&entry.mp_filter, &mut references);
+                let peerings = entry
+                    .mp_peerings
+                    .iter()
+                    .map(|peering_action| {
+                        let peering = &peering_action.mp_peering;
+                        Ok(PolicyPeering {
+                            remote_as: serde_json::to_value(&peering.remote_as)?,
+                            remote_router: peering
+                                .remote_router
+                                .as_ref()
+                                .map(serde_json::to_value)
+                                .transpose()?,
+                            local_router: peering
+                                .local_router
+                                .as_ref()
+                                .map(serde_json::to_value)
+                                .transpose()?,
+                            actions: serde_json::to_value(&peering_action.actions)?,
+                        })
+                    })
+                    .collect::<Result<_>>()?;
+                rules.push(PolicyRule {
+                    rule_index: rules.len() as i32,
+                    address_family,
+                    cast_type,
+                    filter: serde_json::to_value(&entry.mp_filter)?,
+                    peerings,
+                    references,
+                });
+            }
+        }
+    }
+    Ok(rules)
+}
+
+/// Objects the peerings of `peering_set` reference.
+pub fn peering_set_references(peering_set: &PeeringSet) -> BTreeSet<PolicyReference> {
+    let mut references = BTreeSet::new();
+    for peering in &peering_set.peerings {
+        as_expr_references(&peering.remote_as, &mut references);
+    }
+    references
+}
+
+/// Objects the filters of `filter_set` reference.
+pub fn filter_set_references(filter_set: &FilterSet) -> BTreeSet<PolicyReference> {
+    let mut references = BTreeSet::new();
+    for filter in &filter_set.filters {
+        filter_references(filter, &mut references);
+    }
+    references
+}
+
+fn reference(referenced_type: &'static str, name: &str, in_peering: bool) -> PolicyReference {
+    PolicyReference {
+        referenced_type,
+        referenced_name: name.into(),
+        in_peering,
+    }
+}
+
+fn as_expr_references(expr: &AsExpr, references: &mut BTreeSet<PolicyReference>) {
+    match expr {
+        AsExpr::Single(AsName::Num(num)) => {
+            references.insert(reference("as_num", &format!("AS{num}"), true));
+        }
+        AsExpr::Single(AsName::Set(name)) => {
+            references.insert(reference("as_set", name, true));
+        }
+        AsExpr::Single(AsName::Any | AsName::Invalid(_)) => {}
+        AsExpr::PeeringSet(name) => {
+            references.insert(reference("peering_set", name, true));
+        }
+        AsExpr::And { left, right }
+        | AsExpr::Or { left, right }
+        | AsExpr::Except { left, right } => {
+            as_expr_references(left, references);
+            as_expr_references(right, references);
+        }
+        AsExpr::Group(expr) => as_expr_references(expr, references),
+    }
+}
+
+fn filter_references(filter: &Filter, references: &mut BTreeSet<PolicyReference>) {
+    match filter {
+        Filter::FilterSet(name) => {
+            references.insert(reference("filter_set", name, false));
+        }
+        Filter::RouteSet(name, _) => {
+            references.insert(reference("route_set", name, false));
+        }
+        Filter::AsNum(num, _) => {
+            references.insert(reference("as_num", &format!("AS{num}"), false));
+        }
+        Filter::AsSet(name, _) => {
+            references.insert(reference("as_set", name, false));
+        }
+        Filter::And { left, right } | Filter::Or { left, right } => {
+            filter_references(left, references);
+            filter_references(right, references);
+        }
+        Filter::Not(filter) | Filter::Group(filter) => filter_references(filter, references),
+        Filter::Any
+        | Filter::AddrPrefixSet(_)
+        | Filter::AsPathRE(_)
+        | Filter::Community(_)
+        | Filter::Unknown(_)
+        | Filter::Invalid(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use route_verification_v0_2::ir::{AsName, RangeOperator, RouteSetMember};
+
+    use super::*;
+
+    /// Upstream variant names, as listed by `serde` when rejecting an
+    /// unknown variant.
+    fn upstream_variant_names() -> HashSet<String> {
+        let err = serde_json::from_str::<ReportItem>(r#""__unknown__""#).unwrap_err();
+        let message = err.to_string();
+        let expected = message
+            .split("expected one of ")
+            .nth(1)
+            .expect("serde should list the expected variants");
+        expected
+            .split(", ")
+            .map(|name| name.split('`').nth(1).unwrap().to_string())
+            .collect()
+    }
+
+    fn variant_name(item: &ReportItem) -> String {
+        match serde_json::to_value(item).unwrap() {
+            serde_json::Value::String(name) => name,
+            serde_json::Value::Object(map) => map.keys().next().unwrap().clone(),
+            other => panic!("Unexpected serialization {other:?}"),
+        }
+    }
+
+    fn one_of_each_item() -> Vec<ReportItem> {
+        use ReportItem::*;
+        let s = String::new;
+        vec![
+            SkipAsRegexWithTilde(s()),
+            SkipAsRegexPathWithSet,
+            SkipCommunityCheckUnimplemented(Box::default()),
+            UnrecordedAutNum(0),
+            UnrecImportEmpty,
+            UnrecExportEmpty,
+            UnrecordedAsSet(s()),
+            UnrecordedAsRoutes(0),
+            UnrecordedAsSetRoute(s()),
+            UnrecordedSomeAsSetRoute(s()),
+            UnrecordedRouteSet(s()),
+            UnrecordedPeeringSet(s()),
+            UnrecordedFilterSet(s()),
+            SpecAsIsOriginButNoRoute(0),
+            SpecAsSetContainsOriginButNoRoute(s(), 0),
+            SpecExportCustomers,
+            SpecImportFromNeighbor,
+            SpecTier1Pair,
+            SpecImportPeerOIFPS,
+            SpecImportCustomerOIFPS,
+            SpecUphillTier1,
+            SpecUphill,
+            MatchFilter,
+            MatchFilterAsNum(0, RangeOperator::NoOp),
+            MatchFilterAsSet(s(), RangeOperator::NoOp),
+            MatchFilterPrefixes,
+            MatchFilterRouteSet(s()),
+            MatchRemoteAsNum(0),
+            MatchRemoteAsSet(s()),
+            MatchExceptPeeringRight,
+            MatchPeering,
+            MatchRegex(s()),
+            RpslInvalidAsName(s()),
+            RpslInvalidFilter(s()),
+            RpslInvalidAsRegex(s()),
+            RpslUnknownFilter(s()),
+            RecCheckFilter,
+            RecFilterRouteSet(s()),
+            RecFilterRouteSetMember(Box::new(RouteSetMember::NameOp(s(), RangeOperator::NoOp))),
+            RecFilterAsSet(s()),
+            RecFilterAsName(Box::new(AsName::Any)),
+            RecFilterAnd,
+            RecFilterOr,
+            RecFilterNot,
+            RecCheckSetMember(s()),
+            RecCheckRemoteAs,
+            RecRemoteAsName(Box::new(AsName::Any)),
+            RecRemoteAsSet(s()),
+            RecRemotePeeringSet(s()),
+            RecPeeringAnd,
+            RecPeeringOr,
+            RecPeeringExcept,
+        ]
+    }
+
+    #[test]
+    fn every_upstream_variant_maps_to_distinct_type() {
+        let items = one_of_each_item();
+        let sampled: HashSet<_> = items.iter().map(variant_name).collect();
+        assert_eq!(sampled, upstream_variant_names());
+
+        let mut seen = HashMap::new();
+        for item in &items {
+            let item_type = report_item(item).specific_case;
+            if let Some(other) = seen.insert(item_type, variant_name(item)) {
+                panic!(
+                    "{} and {} both map to `{}`",
+                    other,
+                    variant_name(item),
+                    item_type.as_str()
+                );
+            }
+        }
+        let all: HashSet<_> = ReportItemType::ALL.iter().copied().collect();
+        let mapped: HashSet<_> = seen.into_keys().collect();
+        assert_eq!(mapped, all, "Every database label should be reachable.");
+    }
+
+    #[cfg(feature = "native_mrt")]
+    #[test]
+    fn native_lines_match_bgpdump_lines() {
+        let lines = stream_mrt("fixtures/rib.mrt.bz2", None)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(lines.len(), 3);
+        for line in lines {
+            assert_eq!(line, Line::from_raw(line.raw.clone()).unwrap());
+        }
+    }
+
+    #[test]
+    fn parse_update_lines() {
+        let announcement = parse_update(
+            "BGP4MP_ET|1687212002.000123|A|2001:db8::1|4200000001|2001:db8:beef::/48|4200000001 6939 6939|IGP|2001:db8::1|0|0||NAG|||\n".into(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(announcement.update.timestamp, 1687212002);
+        assert_eq!(announcement.update.microseconds, 123);
+        assert_eq!(announcement.update.peer_as, 4200000001);
+        assert!(!announcement.update.withdrawal);
+        let line = announcement.announcement.unwrap();
+        assert_eq!(
+            observed_route(&line).path.as_path,
+            "4200000001 6939",
+            "Prepending should be removed like in RIB lines."
+        );
+
+        let withdrawal =
+            parse_update("BGP4MP|1687212000|W|192.0.2.1|64496|198.51.100.0/24\n".into())
+                .unwrap()
+                .unwrap();
+        assert!(withdrawal.update.withdrawal);
+        assert!(withdrawal.announcement.is_none());
+        assert_eq!(
+            withdrawal.update.address_prefix.to_string(),
+            "198.51.100.0/24"
+        );
+
+        let state = "BGP4MP|1687212003|STATE|192.0.2.1|64496|1|6\n";
+        assert!(parse_update(state.into()).unwrap().is_none());
+        assert!(parse_update("TABLE_DUMP2|1687212000|B|192.0.2.1\n".into()).is_err());
+    }
+
+    #[test]
+    fn flattened_policy_rules() {
+        let versions: Versions = serde_json::from_value(serde_json::json!({
+            "any": {"any": [{
+                "mp_peerings": [{"mp_peering": {"remote_as": {"Single": {"Num": 3356}}}}],
+                "mp_filter": {"FilterSet": "fltr-bogons"}
+            }]},
+            "ipv6": {"unicast": [{
+                "mp_peerings": [
+                    {"mp_peering": {"remote_as": {"Single": {"Set": "AS-FOO"}}}},
+                    {"mp_peering": {"remote_as": {"PeeringSet": "prng-bar"}}}
+                ],
+                "mp_filter": "Any"
+            }]}
+        }))
+        .unwrap();
+        let rules = policy_rules(&versions).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(
+            (
+                rules[0].rule_index,
+                rules[0].address_family,
+                rules[0].cast_type
+            ),
+            (0, "any", "any")
+        );
+        assert_eq!(
+            rules[0].filter,
+            serde_json::json!({"FilterSet": "fltr-bogons"})
+        );
+        assert_eq!(
+            (
+                rules[1].rule_index,
+                rules[1].address_family,
+                rules[1].cast_type
+            ),
+            (1, "ipv6", "unicast")
+        );
+        assert_eq!(rules[1].peerings.len(), 2);
+        assert_eq!(rules[1].peerings[1].remote_router, None);
+
+        assert_eq!(
+            rules[0].references,
+            BTreeSet::from([
+                reference("as_num", "AS3356", true),
+                reference("filter_set", "fltr-bogons", false),
+            ])
+        );
+        assert_eq!(
+            rules[1].references,
+            BTreeSet::from([
+                reference("as_set", "AS-FOO", true),
+                reference("peering_set", "prng-bar", true),
+            ])
+        );
+    }
+
+    #[cfg(feature = "native_mrt")]
+    #[test]
+    fn native_update_stream() {
+        let updates = stream_updates("fixtures/updates.mrt", None)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let withdrawals: Vec<_> = updates.iter().map(|u| u.update.withdrawal).collect();
+        assert_eq!(withdrawals, [true, false, false, false, true, false]);
+        assert!(updates
+            .iter()
+            .all(|u| u.announcement.is_some() != u.update.withdrawal));
+    }
+}
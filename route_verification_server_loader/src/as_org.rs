@@ -0,0 +1,312 @@
+//! Load CAIDA AS-to-Organization files, linking ASes to the organizations
+//! that hold them.
+//! Both the pipe-separated `*.as-org2info.txt` layout and the newer
+//! `*.as-org2info.jsonl` layout are read, plain or `.gz`
+//! with the `gzip` feature.
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "gzip")]
+use flate2::read::MultiGzDecoder;
+use log::{debug, error, info};
+use serde_json::Value;
+use sqlx::{postgres::PgQueryResult, Pool, Postgres};
+
+/// Organization holding ASes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Organization {
+    pub org_id: String,
+    pub org_name: String,
+    pub country: String,
+    /// Registry the record came from, e.g., `ARIN`.
+    pub source: String,
+}
+
+/// Organization holding an AS.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AsOrganization {
+    pub as_num: u32,
+    pub as_name: String,
+    pub org_id: String,
+    pub source: String,
+}
+
+/// Record in an as2org file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum As2OrgRecord {
+    Organization(Organization),
+    As(AsOrganization),
+}
+
+/// Section of a pipe-separated as2org file,
+/// set by its `# format:` comment lines.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum As2OrgSection {
+    /// `org_id|changed|org_name|country|source`.
+    Organization,
+    /// `aut|changed|aut_name|org_id|opaque_id|source`.
+    As,
+}
+
+/// Load the organizations and AS-to-organization links in `as2org_file`,
+/// updating those loaded before.
+pub async fn as2org_db(pool: &Pool<Postgres>, as2org_file: &str) -> Result<()> {
+    let file = File::open(as2org_file).with_context(|| format!("opening {as2org_file}"))?;
+    let reader = match as2org_file.ends_with(".gz") {
+        true => gz_reader(file)?,
+        false => Box::new(BufReader::new(file)),
+    };
+
+    let mut organizations = Vec::new();
+    let mut ases = Vec::new();
+    let mut section = None;
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.with_context(|| format!("reading line {} of {as2org_file}", index + 1))?;
+        match parse_as2org_line(&mut section, &line) {
+            Ok(Some(As2OrgRecord::Organization(organization))) => organizations.push(organization),
+            Ok(Some(As2OrgRecord::As(as_organization))) => ases.push(as_organization),
+            Ok(None) => {}
+            Err(why) => error!("Skipping line {} of {as2org_file}: {why:?}", index + 1),
+        }
+    }
+    info!(
+        "Loading {} organizations and {} ASes from {as2org_file}.",
+        organizations.len(),
+        ases.len()
+    );
+
+    // ASes reference their organizations.
+    for organization in &organizations {
+        debug!("Inserting organization {}", organization.org_id);
+        if let Err(why) = insert_organization(pool, organization).await {
+            error!(
+                "Failed to insert organization {}: {why:?}",
+                organization.org_id
+            );
+        }
+    }
+    for as_organization in &ases {
+        debug!(
+            "Inserting AS{} of organization {}",
+            as_organization.as_num, as_organization.org_id
+        );
+        if let Err(why) = insert_as_organization(pool, as_organization).await {
+            error!(
+                "Failed to link AS{} to organization {}: {why:?}",
+                as_organization.as_num, as_organization.org_id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "gzip")]
+fn gz_reader(file: File) -> Result<Box<dyn BufRead>> {
+    Ok(Box::new(BufReader::new(MultiGzDecoder::new(file))))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gz_reader(_file: File) -> Result<Box<dyn BufRead>> {
+    bail!("Enable the `gzip` feature to read `.gz` files, or decompress them first.")
+}
+
+/// Parse `line` of an as2org file in either layout.
+/// `None` for blank and comment lines.
+/// `section` tracks the pipe-separated layout's current section.
+pub fn parse_as2org_line(
+    section: &mut Option<As2OrgSection>,
+    line: &str,
+) -> Result<Option<As2OrgRecord>> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if let Some(comment) = line.strip_prefix('#') {
+        if let Some(format) = comment.trim().strip_prefix("format:") {
+            *section = match format.split('|').next() {
+                Some("org_id") => Some(As2OrgSection::Organization),
+                Some("aut") => Some(As2OrgSection::As),
+                _ => None,
+            };
+        }
+        return Ok(None);
+    }
+    if line.starts_with('{') {
+        return parse_json_line(line).map(Some);
+    }
+
+    let fields: Vec<_> = line.split('|').collect();
+    Ok(Some(match (*section, &fields[..]) {
+        (Some(As2OrgSection::Organization), &[org_id, _changed, org_name, country, source]) => {
+            As2OrgRecord::Organization(Organization {
+                org_id: org_id.into(),
+                org_name: org_name.into(),
+                country: country.into(),
+                source: source.into(),
+            })
+        }
+        (Some(As2OrgSection::As), &[aut, _changed, aut_name, org_id, _opaque_id, source]) => {
+            As2OrgRecord::As(AsOrganization {
+                as_num: aut.parse()?,
+                as_name: aut_name.into(),
+                org_id: org_id.into(),
+                source: source.into(),
+            })
+        }
+        (None, _) => bail!("record before any `# format:` line"),
+        _ => bail!("unexpected number of fields"),
+    }))
+}
+
+fn parse_json_line(line: &str) -> Result<As2OrgRecord> {
+    let value: Value = serde_json::from_str(line)?;
+    let field = |name: &str| -> Result<String> {
+        Ok(value[name]
+            .as_str()
+            .with_context(|| format!("missing `{name}`"))?
+            .into())
+    };
+    Ok(match value["type"].as_str() {
+        Some("Organization") => As2OrgRecord::Organization(Organization {
+            org_id: field("organizationId")?,
+            org_name: field("name")?,
+            country: field("country")?,
+            source: field("source")?,
+        }),
+        Some("ASN") => As2OrgRecord::As(AsOrganization {
+            as_num: field("asn")?.parse()?,
+            as_name: field("name")?,
+            org_id: field("organizationId")?,
+            source: field("source")?,
+        }),
+        _ => bail!("unknown record type"),
+    })
+}
+
+/// ASes held by the same organization as `as_num`, excluding itself.
+pub async fn siblings(pool: &Pool<Postgres>, as_num: u32) -> Result<Vec<u32>> {
+    let siblings = sqlx::query_scalar!(
+        r#"SELECT sibling AS "sibling!" FROM as_sibling WHERE as_num = $1"#,
+        as_num as i32
+    )
+    .fetch_all(pool)
+    .await?;
+    // Sorted unsigned, since 32-bit ASNs wrap around in `int`.
+    let mut siblings: Vec<_> = siblings.into_iter().map(|sibling| sibling as u32).collect();
+    siblings.sort_unstable();
+    Ok(siblings)
+}
+
+/// Organization holding `as_num`, if known.
+pub async fn organization_of(pool: &Pool<Postgres>, as_num: u32) -> Result<Option<Organization>> {
+    let organization = sqlx::query_as!(
+        Organization,
+        r#"SELECT o.org_id, o.org_name, o.country, o.source
+        FROM as_organization a
+        JOIN organization o ON o.org_id = a.org_id
+        WHERE a.as_num = $1"#,
+        as_num as i32
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(organization)
+}
+
+async fn insert_organization(
+    pool: &Pool<Postgres>,
+    organization: &Organization,
+) -> sqlx::Result<PgQueryResult> {
+    sqlx::query!(
+        r#"INSERT INTO organization(org_id, org_name, country, source) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (org_id) DO UPDATE
+        SET org_name = EXCLUDED.org_name, country = EXCLUDED.country, source = EXCLUDED.source, recorded_time = now()"#,
+        organization.org_id,
+        organization.org_name,
+        organization.country,
+        organization.source,
+    )
+    .execute(pool)
+    .await
+}
+
+async fn insert_as_organization(
+    pool: &Pool<Postgres>,
+    as_organization: &AsOrganization,
+) -> sqlx::Result<PgQueryResult> {
+    sqlx::query!(
+        r#"INSERT INTO as_organization(as_num, org_id, as_name, source) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (as_num) DO UPDATE
+        SET org_id = EXCLUDED.org_id, as_name = EXCLUDED.as_name, source = EXCLUDED.source, recorded_time = now()"#,
+        as_organization.as_num as i32,
+        as_organization.org_id,
+        as_organization.as_name,
+        as_organization.source,
+    )
+    .execute(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Executor, PgPool};
+
+    use super::*;
+
+    #[test]
+    fn both_layouts() {
+        let mut section = None;
+        let lines = [
+            "# name: AS Org",
+            "# format:org_id|changed|org_name|country|source",
+            "LVLT-ARIN|20120130|Level 3 Parent, LLC|US|ARIN",
+            "# format:aut|changed|aut_name|org_id|opaque_id|source",
+            "3356|20120130|LEVEL3|LVLT-ARIN|e5e3b9c13678dfc483fb1f819d70883c_ARIN|ARIN",
+            r#"{"asn":"3549","changed":"20120130","name":"LVLT-3549","opaqueId":"x_ARIN","organizationId":"LVLT-ARIN","source":"ARIN","type":"ASN"}"#,
+        ];
+        let records: Vec<_> = lines
+            .iter()
+            .filter_map(|line| parse_as2org_line(&mut section, line).unwrap())
+            .collect();
+        assert_eq!(
+            records,
+            [
+                As2OrgRecord::Organization(Organization {
+                    org_id: "LVLT-ARIN".into(),
+                    org_name: "Level 3 Parent, LLC".into(),
+                    country: "US".into(),
+                    source: "ARIN".into(),
+                }),
+                As2OrgRecord::As(AsOrganization {
+                    as_num: 3356,
+                    as_name: "LEVEL3".into(),
+                    org_id: "LVLT-ARIN".into(),
+                    source: "ARIN".into(),
+                }),
+                As2OrgRecord::As(AsOrganization {
+                    as_num: 3549,
+                    as_name: "LVLT-3549".into(),
+                    org_id: "LVLT-ARIN".into(),
+                    source: "ARIN".into(),
+                }),
+            ]
+        );
+        assert!(parse_as2org_line(&mut None, "3356|20120130|LEVEL3").is_err());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[sqlx::test]
+    async fn gzipped_siblings(pool: PgPool) -> Result<()> {
+        pool.execute(include_str!("../../demo_v1.sql")).await?;
+        pool.execute(include_str!("../../trigger_only.sql")).await?;
+        as2org_db(&pool, "fixtures/20230701.as-org2info.txt.gz").await?;
+
+        let organization = organization_of(&pool, 4_200_000_000).await?.unwrap();
+        assert_eq!(organization.org_id, "LVLT-ARIN");
+        assert_eq!(siblings(&pool, 3356).await?, [3549, 4_200_000_000]);
+        Ok(())
+    }
+}
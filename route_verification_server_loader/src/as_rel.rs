@@ -0,0 +1,385 @@
+//! Load CAIDA AS relationship files, one snapshot per file,
+//! and look up relationships as of a date.
+use std::{fs::read_dir, path::Path};
+
+use anyhow::{bail, Context, Result};
+use log::{debug, error, info};
+use sqlx::{
+    postgres::{PgConnection, PgQueryResult},
+    Pool, Postgres,
+};
+
+use crate::{adapter::*, hierarchy::compute_hierarchy, run::hash_file};
+
+/// Suffix of CAIDA AS relationship file names.
+const AS_REL_SUFFIX: &str = ".as-rel.bz2";
+
+/// Relationship of `as_1` to `as_2` that differs between two snapshots.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RelationshipChange {
+    pub as_1: u32,
+    pub as_2: u32,
+    /// `None` if the ASes are not linked in that snapshot.
+    pub old: Option<Relationship>,
+    pub new: Option<Relationship>,
+}
+
+/// Load every `*.as-rel.bz2` file in `dir`, oldest snapshot first.
+pub async fn as_relationship_dir(pool: &Pool<Postgres>, dir: &str) -> Result<()> {
+    let mut as_rel_files = Vec::new();
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        if path.to_string_lossy().ends_with(AS_REL_SUFFIX) {
+            as_rel_files.push(path);
+        }
+    }
+    as_rel_files.sort();
+    info!(
+        "Loading {} AS relationship files in {dir}.",
+        as_rel_files.len()
+    );
+    for as_rel_file in as_rel_files {
+        let as_rel_file = as_rel_file.to_string_lossy();
+        if let Err(why) = as_relationship_db(pool, &as_rel_file).await {
+            error!("Failed to load {as_rel_file}: {why:?}");
+        }
+    }
+    Ok(())
+}
+
+/// Load the relationships and tier-1 clique in `as_rel_file`
+/// as the snapshot dated in its file name,
+/// tagging each row with the file it came from.
+/// Files loaded before are skipped.
+/// The file is parsed before anything is inserted,
+/// and its source and rows are inserted in one transaction,
+/// so a failed load leaves no partial snapshot behind.
+pub async fn as_relationship_db(pool: &Pool<Postgres>, as_rel_file: &str) -> Result<()> {
+    let Some(date) = snapshot_date(as_rel_file) else {
+        bail!("{as_rel_file} does not start with a `YYYYMMDD` snapshot date.");
+    };
+    let db = AsRelDb::load_bz(as_rel_file)?;
+
+    let mut transaction = pool.begin().await?;
+    let Some(source) = insert_as_rel_source(&mut transaction, as_rel_file, &date).await? else {
+        info!("Skipping {as_rel_file}, loaded before.");
+        return Ok(());
+    };
+    info!("Loading {as_rel_file} as the snapshot of {date}.");
+
+    for &as_num in &db.clique {
+        debug!("Inserting tier-1 AS {as_num}");
+        insert_tier1(&mut transaction, as_num as i32, source)
+            .await
+            .with_context(|| format!("inserting tier-1 AS {as_num}"))?;
+    }
+
+    for ((from, to), relationship) in &db.source2dest {
+        match (from, to, relationship) {
+            (provider, customer, Relationship::P2C) | (customer, provider, Relationship::C2P) => {
+                debug!(
+                    "Inserting provider-customer relationship {} -> {}",
+                    from, to
+                );
+                insert_provide_customer(
+                    &mut transaction,
+                    *provider as i32,
+                    *customer as i32,
+                    source,
+                )
+                .await
+                .with_context(|| {
+                    format!("inserting provider-customer relationship {from} -> {to}")
+                })?;
+            }
+            (_, _, Relationship::P2P) => {
+                debug!("Inserting peer relationship {} -> {}", from, to);
+                let (peer_1, peer_2) = canonical_peers(*from as i32, *to as i32);
+                let result = insert_peer(&mut transaction, peer_1, peer_2, source)
+                    .await
+                    .with_context(|| format!("inserting peer relationship {from} -> {to}"))?;
+                if result.rows_affected() == 0 {
+                    debug!("Skipping reverse of peer relationship {} -> {}", to, from)
+                }
+            }
+        }
+    }
+    transaction.commit().await?;
+
+    compute_hierarchy(pool, source).await
+}
+
+/// Snapshot date CAIDA puts at the start of AS relationship file names,
+/// e.g., `20230701` in `20230701.as-rel.bz2`.
+pub fn snapshot_date<P: AsRef<Path>>(as_rel_file: P) -> Option<String> {
+    let name = as_rel_file.as_ref().file_name()?.to_str()?;
+    let date = name.get(..8)?;
+    date.bytes()
+        .all(|byte| byte.is_ascii_digit())
+        .then(|| date.into())
+}
+
+/// Order of peers `peer` stores a peer-to-peer link in.
+pub fn canonical_peers(as_1: i32, as_2: i32) -> (i32, i32) {
+    (as_1.min(as_2), as_1.max(as_2))
+}
+
+/// Relationship of `as_1` to `as_2` in the latest snapshot,
+/// whichever way the link is stored.
+/// `None` if they are not linked.
+pub async fn relationship(
+    pool: &Pool<Postgres>,
+    as_1: u32,
+    as_2: u32,
+) -> Result<Option<Relationship>> {
+    let (as_1, as_2) = (as_1 as i32, as_2 as i32);
+    let (peer_1, peer_2) = canonical_peers(as_1, as_2);
+    let row = sqlx::query!(
+        r#"WITH latest AS (
+            SELECT as_rel_source_id FROM as_rel_source
+            ORDER BY snapshot_date DESC, as_rel_source_id DESC
+            LIMIT 1
+        )
+        SELECT 'p2c' AS "relationship!" FROM provide_customer
+        WHERE provider = $1 AND customer = $2
+            AND as_rel_source = (SELECT as_rel_source_id FROM latest)
+        UNION ALL
+        SELECT 'c2p' FROM provide_customer
+        WHERE provider = $2 AND customer = $1
+            AND as_rel_source = (SELECT as_rel_source_id FROM latest)
+        UNION ALL
+        SELECT 'p2p' FROM peer
+        WHERE peer_1 = $3 AND peer_2 = $4
+            AND as_rel_source = (SELECT as_rel_source_id FROM latest)
+        LIMIT 1"#,
+        as_1,
+        as_2,
+        peer_1,
+        peer_2,
+    )
+    .fetch_optional(pool)
+    .await?;
+    row.map(|row| parse_relationship(&row.relationship))
+        .transpose()
+}
+
+/// Relationship of `as_1` to `as_2` in the latest snapshot
+/// on or before `date`, e.g., `2023-07-01` or `20230701`.
+/// `None` if they are not linked then.
+pub async fn relationship_on(
+    pool: &Pool<Postgres>,
+    as_1: u32,
+    as_2: u32,
+    date: &str,
+) -> Result<Option<Relationship>> {
+    let row = sqlx::query!(
+        r#"SELECT r.relationship AS "relationship!"
+        FROM as_relationship r
+        WHERE r.as_rel_source = (
+            SELECT as_rel_source_id FROM as_rel_source
+            WHERE snapshot_date <= CAST($3::text AS date)
+            ORDER BY snapshot_date DESC, as_rel_source_id DESC
+            LIMIT 1
+        ) AND r.as_1 = $1 AND r.as_2 = $2
+        LIMIT 1"#,
+        as_1 as i32,
+        as_2 as i32,
+        date,
+    )
+    .fetch_optional(pool)
+    .await?;
+    row.map(|row| parse_relationship(&row.relationship))
+        .transpose()
+}
+
+/// Relationships that differ between the latest snapshots
+/// on or before `old_date` and `new_date`,
+/// each listed once with `as_1 < as_2`.
+pub async fn relationship_changes(
+    pool: &Pool<Postgres>,
+    old_date: &str,
+    new_date: &str,
+) -> Result<Vec<RelationshipChange>> {
+    let rows = sqlx::query!(
+        r#"WITH snapshot AS (
+            SELECT d.date, (
+                SELECT as_rel_source_id FROM as_rel_source
+                WHERE snapshot_date <= CAST(d.date AS date)
+                ORDER BY snapshot_date DESC, as_rel_source_id DESC
+                LIMIT 1
+            ) AS as_rel_source
+            FROM unnest(ARRAY[$1::text, $2::text]) AS d(date)
+        ), old AS (
+            SELECT DISTINCT as_1, as_2, relationship FROM as_relationship
+            WHERE as_rel_source = (SELECT as_rel_source FROM snapshot WHERE date = $1)
+                AND as_1 < as_2
+        ), new AS (
+            SELECT DISTINCT as_1, as_2, relationship FROM as_relationship
+            WHERE as_rel_source = (SELECT as_rel_source FROM snapshot WHERE date = $2)
+                AND as_1 < as_2
+        )
+        SELECT
+            COALESCE(old.as_1, new.as_1) AS "as_1!",
+            COALESCE(old.as_2, new.as_2) AS "as_2!",
+            old.relationship AS "old?",
+            new.relationship AS "new?"
+        FROM old FULL JOIN new ON old.as_1 = new.as_1 AND old.as_2 = new.as_2
+        WHERE old.relationship IS DISTINCT FROM new.relationship
+        ORDER BY 1, 2"#,
+        old_date,
+        new_date,
+    )
+    .fetch_all(pool)
+    .await?;
+    rows.into_iter()
+        .map(|row| {
+            Ok(RelationshipChange {
+                as_1: row.as_1 as u32,
+                as_2: row.as_2 as u32,
+                old: row.old.as_deref().map(parse_relationship).transpose()?,
+                new: row.new.as_deref().map(parse_relationship).transpose()?,
+            })
+        })
+        .collect()
+}
+
+/// Label of `relationship` in the `as_relationship` view.
+pub const fn relationship_label(relationship: Relationship) -> &'static str {
+    match relationship {
+        Relationship::P2C => "p2c",
+        Relationship::P2P => "p2p",
+        Relationship::C2P => "c2p",
+    }
+}
+
+fn parse_relationship(label: &str) -> Result<Relationship> {
+    Ok(match label {
+        "p2c" => Relationship::P2C,
+        "p2p" => Relationship::P2P,
+        "c2p" => Relationship::C2P,
+        other => bail!("invalid AS relationship `{other}`"),
+    })
+}
+
+/// Record `as_rel_file` as the relationship source of snapshot `date`.
+/// `None` if the same file was loaded before.
+async fn insert_as_rel_source(
+    conn: &mut PgConnection,
+    as_rel_file: &str,
+    date: &str,
+) -> Result<Option<i32>> {
+    let file_name = Path::new(as_rel_file)
+        .file_name()
+        .context("AS relationship file without a name")?
+        .to_string_lossy();
+    let row = sqlx::query!(
+        r#"INSERT INTO as_rel_source(file_name, snapshot_date, file_hash)
+        VALUES ($1, to_date($2, 'YYYYMMDD'), $3)
+        ON CONFLICT (file_hash) DO NOTHING
+        RETURNING as_rel_source_id"#,
+        &file_name,
+        date,
+        hash_file(as_rel_file)?,
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(row.map(|row| row.as_rel_source_id))
+}
+
+async fn insert_tier1(
+    conn: &mut PgConnection,
+    as_num: i32,
+    as_rel_source: i32,
+) -> sqlx::Result<PgQueryResult> {
+    sqlx::query!(
+        "INSERT INTO tier1(as_num, as_rel_source) VALUES ($1, $2)",
+        as_num,
+        as_rel_source
+    )
+    .execute(conn)
+    .await
+}
+
+async fn insert_provide_customer(
+    conn: &mut PgConnection,
+    provider: i32,
+    customer: i32,
+    as_rel_source: i32,
+) -> sqlx::Result<PgQueryResult> {
+    sqlx::query!(
+        "INSERT INTO provide_customer(provider, customer, as_rel_source) VALUES ($1, $2, $3)",
+        provider,
+        customer,
+        as_rel_source
+    )
+    .execute(conn)
+    .await
+}
+
+async fn insert_peer(
+    conn: &mut PgConnection,
+    peer_1: i32,
+    peer_2: i32,
+    as_rel_source: i32,
+) -> sqlx::Result<PgQueryResult> {
+    sqlx::query!(
+        "INSERT INTO peer(peer_1, peer_2, as_rel_source) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+        peer_1,
+        peer_2,
+        as_rel_source
+    )
+    .execute(conn)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Executor, PgPool};
+
+    use super::*;
+
+    #[test]
+    fn snapshot_dates() {
+        assert_eq!(
+            snapshot_date("data/20230701.as-rel.bz2").as_deref(),
+            Some("20230701")
+        );
+        assert_eq!(snapshot_date("as-rel.bz2"), None);
+    }
+
+    #[test]
+    fn peers_in_either_order() {
+        assert_eq!(canonical_peers(3356, 174), (174, 3356));
+        assert_eq!(canonical_peers(174, 3356), (174, 3356));
+    }
+
+    #[test]
+    fn relationship_labels() {
+        for relationship in [Relationship::P2C, Relationship::P2P, Relationship::C2P] {
+            let label = relationship_label(relationship);
+            assert_eq!(parse_relationship(label).unwrap(), relationship);
+        }
+        assert!(parse_relationship("s2s").is_err());
+    }
+
+    #[sqlx::test]
+    async fn stored_32_bit_relationships(pool: PgPool) -> Result<()> {
+        pool.execute(include_str!("../../demo_v1.sql")).await?;
+        pool.execute(include_str!("../../trigger_only.sql")).await?;
+        as_relationship_db(&pool, "fixtures/20230701.as-rel.bz2").await?;
+
+        assert_eq!(
+            relationship(&pool, 3356, 4_200_000_000).await?,
+            Some(Relationship::P2C)
+        );
+        assert_eq!(
+            relationship(&pool, 4_200_000_001, 64496).await?,
+            Some(Relationship::P2P)
+        );
+        assert_eq!(
+            relationship_on(&pool, 4_200_000_001, 4_200_000_000, "2023-07-01").await?,
+            Some(Relationship::C2P)
+        );
+        Ok(())
+    }
+}
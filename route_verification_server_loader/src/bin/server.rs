@@ -0,0 +1,414 @@
+//! REST API server over the database the loader fills,
+//! serving the endpoints of `flask_RestAPI/app.py` as typed JSON.
+//! Pass the address to listen on, defaulting to `127.0.0.1:5000`.
+//! Endpoints listing report details take `?limit=` and `?cursor=`,
+//! the `next_cursor` of the previous page.
+//! AS numbers in paths are unsigned, as in RPSL.
+use std::env::args;
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{types::ipnetwork::IpNetwork, Pool, Postgres};
+use tokio::net::TcpListener;
+
+use route_verification_server_loader::{
+    connect,
+    query::{self, *},
+    report_type::{OverallReportType, ReportItemType},
+};
+
+const LISTEN_ADDR: &str = "127.0.0.1:5000";
+
+type Db = State<Pool<Postgres>>;
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+    let pool = connect().await?;
+    let app = app(pool);
+
+    let listen_addr = args().nth(1).unwrap_or_else(|| LISTEN_ADDR.into());
+    let listener = TcpListener::bind(&listen_addr).await?;
+    info!("Listening on {listen_addr}.");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// Routes of every endpoint, querying `pool`.
+fn app(pool: Pool<Postgres>) -> Router {
+    Router::new()
+        .route("/rpsl_obj/:rpsl_obj_name", get(rpsl_obj))
+        .route(
+            "/verification_reports/:observed_route_id",
+            get(verification_reports),
+        )
+        .route("/aut_num/:as_num", get(aut_num))
+        .route(
+            "/as_for_overall_report_type/:overall_report_type",
+            get(as_for_overall_report_type),
+        )
+        .route(
+            "/route_for_overall_report_type_import_by_provider/:overall_report_type",
+            get(route_for_overall_report_type_import_by_provider),
+        )
+        .route(
+            "/as_for_overall_report_type_import_by_provider/:overall_report_type",
+            get(as_for_overall_report_type_import_by_provider),
+        )
+        .route(
+            "/as_lacking_rules_for_import_by_provider",
+            get(as_lacking_rules_for_import_by_provider),
+        )
+        .route(
+            "/as_for_report_item_type/:report_item_type",
+            get(as_for_report_item_type),
+        )
+        .route(
+            "/for_overall_report_type/:overall_report_type",
+            get(for_overall_report_type),
+        )
+        .route(
+            "/for_report_item_type/:report_item_type",
+            get(for_report_item_type),
+        )
+        .route(
+            "/for_address_prefix/:address/:prefix_length",
+            get(for_address_prefix),
+        )
+        .route(
+            "/overall_report_type/:overall_report_type",
+            get(overall_report_type),
+        )
+        .route("/report_for_as/:as_num", get(report_for_as))
+        .route("/route_for_as/:as_num", get(route_for_as))
+        .with_state(pool)
+}
+
+/// Failure of a request, answered as a JSON object like the Flask server's.
+#[derive(Debug)]
+enum ApiError {
+    NotFound,
+    InvalidInput(Value),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(value: sqlx::Error) -> Self {
+        Self::Database(value)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, body) = match self {
+            Self::NotFound => (
+                StatusCode::NOT_FOUND,
+                json!({"information": "Entry not found"}),
+            ),
+            Self::InvalidInput(body) => (StatusCode::BAD_REQUEST, body),
+            Self::Database(why) => {
+                error!("Query failed: {why:?}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    json!({"error": "Database query failed"}),
+                )
+            }
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+fn parse_overall_report_type(label: &str) -> Result<OverallReportType, ApiError> {
+    OverallReportType::from_label(label).ok_or_else(|| {
+        ApiError::InvalidInput(json!({
+            "input_error": "Invalid overall_report_type",
+            "possible_values": OverallReportType::ALL,
+        }))
+    })
+}
+
+fn parse_report_item_type(label: &str) -> Result<ReportItemType, ApiError> {
+    ReportItemType::from_label(label).ok_or_else(|| {
+        ApiError::InvalidInput(json!({
+            "input_error": "Invalid report_item_type",
+            "possible_values": ReportItemType::ALL,
+        }))
+    })
+}
+
+/// `?cursor=…&limit=…` of a paged endpoint.
+#[derive(Debug, Deserialize)]
+struct PageParams {
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+impl PageParams {
+    fn parse(self) -> Result<(Option<Cursor>, i64), ApiError> {
+        let after = match self.cursor {
+            Some(cursor) => Some(Cursor::decode(&cursor).ok_or_else(|| {
+                ApiError::InvalidInput(json!({"input_error": "Invalid cursor", "cursor": cursor}))
+            })?),
+            None => None,
+        };
+        let limit = self.limit.unwrap_or(PAGE_SIZE);
+        if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+            return Err(ApiError::InvalidInput(json!({
+                "input_error": "Invalid limit",
+                "limit": limit,
+                "max_limit": MAX_PAGE_SIZE,
+            })));
+        }
+        Ok((after, limit))
+    }
+}
+
+fn found<T: Serialize>(entry: Option<T>) -> ApiResult<T> {
+    entry.map(Json).ok_or(ApiError::NotFound)
+}
+
+async fn rpsl_obj(State(pool): Db, Path(rpsl_obj_name): Path<String>) -> ApiResult<RpslObj> {
+    found(query::rpsl_obj(&pool, &rpsl_obj_name).await?)
+}
+
+async fn verification_reports(
+    State(pool): Db,
+    Path(observed_route_id): Path<i32>,
+) -> ApiResult<Vec<ExchangeReport>> {
+    Ok(Json(
+        query::verification_reports(&pool, observed_route_id).await?,
+    ))
+}
+
+async fn aut_num(State(pool): Db, Path(as_num): Path<u32>) -> ApiResult<AutNumPolicies> {
+    found(query::aut_num(&pool, as_num as i32).await?)
+}
+
+async fn as_for_overall_report_type(
+    State(pool): Db,
+    Path(overall_report_type): Path<String>,
+) -> ApiResult<Vec<AsReportCount>> {
+    let overall_type = parse_overall_report_type(&overall_report_type)?;
+    Ok(Json(
+        query::as_for_overall_report_type(&pool, overall_type).await?,
+    ))
+}
+
+async fn route_for_overall_report_type_import_by_provider(
+    State(pool): Db,
+    Path(overall_report_type): Path<String>,
+) -> ApiResult<Vec<RouteReportCount>> {
+    let overall_type = parse_overall_report_type(&overall_report_type)?;
+    Ok(Json(
+        query::route_for_overall_report_type_import_by_provider(&pool, overall_type).await?,
+    ))
+}
+
+async fn as_for_overall_report_type_import_by_provider(
+    State(pool): Db,
+    Path(overall_report_type): Path<String>,
+) -> ApiResult<Vec<AsReportCount>> {
+    let overall_type = parse_overall_report_type(&overall_report_type)?;
+    Ok(Json(
+        query::as_for_overall_report_type_import_by_provider(&pool, overall_type).await?,
+    ))
+}
+
+async fn as_lacking_rules_for_import_by_provider(
+    State(pool): Db,
+) -> ApiResult<Vec<AsReportItemCount>> {
+    Ok(Json(
+        query::as_lacking_rules_for_import_by_provider(&pool).await?,
+    ))
+}
+
+async fn as_for_report_item_type(
+    State(pool): Db,
+    Path(report_item_type): Path<String>,
+) -> ApiResult<Vec<AsReportItemCount>> {
+    let specific_case = parse_report_item_type(&report_item_type)?;
+    Ok(Json(
+        query::as_for_report_item_type(&pool, specific_case).await?,
+    ))
+}
+
+async fn for_overall_report_type(
+    State(pool): Db,
+    Path(overall_report_type): Path<String>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<RouteReportItem>> {
+    let overall_type = parse_overall_report_type(&overall_report_type)?;
+    let (after, limit) = params.parse()?;
+    Ok(Json(
+        query::for_overall_report_type(&pool, overall_type, after, limit).await?,
+    ))
+}
+
+async fn for_report_item_type(
+    State(pool): Db,
+    Path(report_item_type): Path<String>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<RouteReportItem>> {
+    let specific_case = parse_report_item_type(&report_item_type)?;
+    let (after, limit) = params.parse()?;
+    Ok(Json(
+        query::for_report_item_type(&pool, specific_case, after, limit).await?,
+    ))
+}
+
+async fn for_address_prefix(
+    State(pool): Db,
+    Path((address, prefix_length)): Path<(String, u8)>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<ReportItemDetail>> {
+    let Ok(address_prefix) = format!("{address}/{prefix_length}").parse::<IpNetwork>() else {
+        return Err(ApiError::InvalidInput(json!({
+            "input_error": "Invalid address or prefix_length",
+            "address": address,
+            "prefix_length": prefix_length,
+        })));
+    };
+    let (after, limit) = params.parse()?;
+    Ok(Json(
+        query::for_address_prefix(&pool, address_prefix, after, limit).await?,
+    ))
+}
+
+async fn overall_report_type(
+    State(pool): Db,
+    Path(overall_report_type): Path<String>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<ExchangeReport>> {
+    let overall_type = parse_overall_report_type(&overall_report_type)?;
+    let (after, limit) = params.parse()?;
+    Ok(Json(
+        query::reports_of_overall_type(&pool, overall_type, after, limit).await?,
+    ))
+}
+
+async fn report_for_as(
+    State(pool): Db,
+    Path(as_num): Path<u32>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<ExchangeReport>> {
+    let (after, limit) = params.parse()?;
+    Ok(Json(
+        query::reports_for_as(&pool, as_num as i32, after, limit).await?,
+    ))
+}
+
+async fn route_for_as(State(pool): Db, Path(as_num): Path<u32>) -> ApiResult<Vec<AsRoute>> {
+    Ok(Json(query::routes_for_as(&pool, as_num as i32).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::to_bytes, http::Request};
+    use sqlx::{postgres::PgPoolOptions, Executor, PgPool};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    /// Answer `uri` with a pool of an unreachable database,
+    /// so requests that query fail with 500.
+    async fn get_json(uri: &str) -> (StatusCode, Value) {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://postgres@127.0.0.1:1/none")
+            .unwrap();
+        get_json_from(pool, uri).await
+    }
+
+    async fn get_json_from(pool: PgPool, uri: &str) -> (StatusCode, Value) {
+        let request = Request::get(uri).body(axum::body::Body::empty()).unwrap();
+        let response = app(pool).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn invalid_report_types() {
+        let (status, body) = get_json("/for_overall_report_type/meh").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["input_error"], "Invalid overall_report_type");
+        assert_eq!(body["possible_values"], json!(OverallReportType::ALL));
+
+        let (status, body) = get_json("/as_for_report_item_type/err_meh").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["possible_values"], json!(ReportItemType::ALL));
+    }
+
+    #[tokio::test]
+    async fn invalid_pages() {
+        for uri in [
+            "/for_overall_report_type/bad?cursor=not-hex",
+            "/overall_report_type/bad?cursor=not-hex",
+            "/report_for_as/3356?cursor=1",
+        ] {
+            let (status, body) = get_json(uri).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["input_error"], "Invalid cursor");
+        }
+
+        for limit in [0, MAX_PAGE_SIZE + 1] {
+            let uri = format!("/for_report_item_type/err_filter?limit={limit}");
+            let (status, body) = get_json(&uri).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["input_error"], "Invalid limit");
+            assert_eq!(body["max_limit"], MAX_PAGE_SIZE);
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_prefixes() {
+        for uri in [
+            "/for_address_prefix/not-an-address/24",
+            "/for_address_prefix/192.0.2.0/33",
+        ] {
+            let (status, body) = get_json(uri).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["input_error"], "Invalid address or prefix_length");
+        }
+    }
+
+    #[sqlx::test]
+    async fn stored_reports(pool: PgPool) -> sqlx::Result<()> {
+        pool.execute(include_str!("../../../demo_v1.sql")).await?;
+        pool.execute(include_str!("../../../trigger_only.sql"))
+            .await?;
+        pool.execute(include_str!("../../fixtures/reports.sql"))
+            .await?;
+
+        let (status, body) = get_json_from(pool.clone(), "/aut_num/4200000000").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["as_num"], 4_200_000_000u32);
+        assert_eq!(body["as_name"], "EXAMPLE-32BIT");
+
+        let (status, body) = get_json_from(pool.clone(), "/for_overall_report_type/bad").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_reports"], json!({"count": 5, "exact": true}));
+        let items = body["items"].as_array().unwrap();
+        assert_eq!(items.len(), 11);
+        assert_eq!(items[0]["report_id"], 3);
+        assert_eq!(items[0]["report_specific_case"], "err_filter");
+        let report = items.iter().find(|item| item["report_id"] == 1).unwrap();
+        assert_eq!(report["source_as"], 3356);
+        assert_eq!(report["destination_as"], 4_200_000_000u32);
+        assert!(body["next_cursor"].is_string());
+
+        let (status, body) = get_json_from(pool, "/route_for_as/4200000000").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.as_array().unwrap().len(), 3);
+        Ok(())
+    }
+}
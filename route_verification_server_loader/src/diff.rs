@@ -0,0 +1,401 @@
+//! Differences between the reports of two verification runs, per hop,
+//! e.g., to see which hops went from bad to ok after an IR update.
+//! Only route paths observed in both runs are compared.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::Result;
+use log::info;
+use serde::Serialize;
+use sqlx::{postgres::PgConnection, types::ipnetwork::IpNetwork, Pool, Postgres};
+
+use crate::{
+    report::ReportItemRecord,
+    report_type::{OverallReportType, ReportItemType},
+};
+
+/// Report on one hop of a route path in one run.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HopReport {
+    pub route_path_id: i32,
+    pub address_prefix: IpNetwork,
+    pub as_path: String,
+    pub hop_index: Option<i32>,
+    pub from_as: i32,
+    pub to_as: i32,
+    pub import: bool,
+    pub overall_type: OverallReportType,
+    /// Items as a multiset, since a report can repeat an item.
+    pub items: Vec<ReportItemRecord>,
+}
+
+/// Identifies a hop across runs.
+type HopKey = (i32, Option<i32>, i32, i32, bool);
+
+impl HopReport {
+    fn key(&self) -> HopKey {
+        (
+            self.route_path_id,
+            self.hop_index,
+            self.from_as,
+            self.to_as,
+            self.import,
+        )
+    }
+}
+
+/// Hop whose report changed between two runs.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct HopDiff {
+    pub route_path_id: i32,
+    pub address_prefix: String,
+    pub as_path: String,
+    pub hop_index: Option<i32>,
+    pub from_as: i32,
+    pub to_as: i32,
+    pub import: bool,
+    /// `None` if the hop has no report in that run.
+    pub old_type: Option<OverallReportType>,
+    pub new_type: Option<OverallReportType>,
+    pub added_items: Vec<ReportItemRecord>,
+    pub removed_items: Vec<ReportItemRecord>,
+}
+
+/// Compare the reports of run `old_run` and `new_run`,
+/// storing the changed hops in `report_diff` and writing them to
+/// `out_file`, as CSV if it ends in `.csv` and JSON otherwise.
+pub async fn diff_runs(
+    pool: &Pool<Postgres>,
+    old_run: i32,
+    new_run: i32,
+    out_file: &str,
+) -> Result<()> {
+    let old = fetch_hop_reports(pool, old_run).await?;
+    let new = fetch_hop_reports(pool, new_run).await?;
+    let diffs = diff_hops(&old, &new);
+
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        "DELETE FROM report_diff WHERE old_run = $1 AND new_run = $2",
+        old_run,
+        new_run,
+    )
+    .execute(&mut *transaction)
+    .await?;
+    for diff in &diffs {
+        insert_report_diff(&mut transaction, old_run, new_run, diff).await?;
+    }
+    transaction.commit().await?;
+
+    let mut writer = BufWriter::new(File::create(out_file)?);
+    match Path::new(out_file).extension() {
+        Some(extension) if extension == "csv" => write_csv(&mut writer, &diffs)?,
+        _ => serde_json::to_writer_pretty(&mut writer, &diffs)?,
+    }
+    writer.flush()?;
+
+    let bad_to_ok = diffs
+        .iter()
+        .filter(|diff| {
+            diff.old_type == Some(OverallReportType::Bad)
+                && diff.new_type == Some(OverallReportType::Ok)
+        })
+        .count();
+    info!(
+        "{} hops changed from run {old_run} to {new_run}, {bad_to_ok} from bad to ok. Wrote {out_file}.",
+        diffs.len()
+    );
+    Ok(())
+}
+
+/// Hops that differ between `old` and `new`,
+/// on route paths present in both.
+pub fn diff_hops(old: &[HopReport], new: &[HopReport]) -> Vec<HopDiff> {
+    let by_key = |reports: &[HopReport]| -> BTreeMap<HopKey, HopReport> {
+        reports
+            .iter()
+            .map(|report| (report.key(), report.clone()))
+            .collect()
+    };
+    let (old, new) = (by_key(old), by_key(new));
+    let old_routes: BTreeSet<_> = old.values().map(|report| report.route_path_id).collect();
+    let new_routes: BTreeSet<_> = new.values().map(|report| report.route_path_id).collect();
+    let keys: BTreeSet<_> = old.keys().chain(new.keys()).copied().collect();
+
+    let mut diffs = Vec::new();
+    for key in keys {
+        let route_path_id = key.0;
+        if !(old_routes.contains(&route_path_id) && new_routes.contains(&route_path_id)) {
+            continue;
+        }
+        let (old_report, new_report) = (old.get(&key), new.get(&key));
+        let old_items = old_report.map_or(&[][..], |report| &report.items);
+        let new_items = new_report.map_or(&[][..], |report| &report.items);
+        let added_items = item_difference(new_items, old_items);
+        let removed_items = item_difference(old_items, new_items);
+        let old_type = old_report.map(|report| report.overall_type);
+        let new_type = new_report.map(|report| report.overall_type);
+        if old_type == new_type && added_items.is_empty() && removed_items.is_empty() {
+            continue;
+        }
+        let report = old_report.or(new_report).expect("key from either run");
+        diffs.push(HopDiff {
+            route_path_id,
+            address_prefix: report.address_prefix.to_string(),
+            as_path: report.as_path.clone(),
+            hop_index: report.hop_index,
+            from_as: report.from_as,
+            to_as: report.to_as,
+            import: report.import,
+            old_type,
+            new_type,
+            added_items,
+            removed_items,
+        });
+    }
+    diffs
+}
+
+/// Items in `items` beyond those in `other`, counting repeated items,
+/// in sorted order.
+fn item_difference(
+    items: &[ReportItemRecord],
+    other: &[ReportItemRecord],
+) -> Vec<ReportItemRecord> {
+    let mut counts: BTreeMap<&ReportItemRecord, isize> = BTreeMap::new();
+    for item in items {
+        *counts.entry(item).or_default() += 1;
+    }
+    for item in other {
+        if let Some(count) = counts.get_mut(item) {
+            *count -= 1;
+        }
+    }
+    counts
+        .into_iter()
+        .flat_map(|(item, count)| std::iter::repeat_n(item, count.max(0) as usize))
+        .cloned()
+        .collect()
+}
+
+/// Reports on every hop of the route paths observed in run `run_id`.
+async fn fetch_hop_reports(pool: &Pool<Postgres>, run_id: i32) -> sqlx::Result<Vec<HopReport>> {
+    let rows = sqlx::query!(
+        r#"SELECT
+            v.route_path_id,
+            p.address_prefix,
+            p.as_path,
+            e.report_id,
+            e.hop_index,
+            e.from_as,
+            e.to_as,
+            e.import,
+            e.overall_type AS "overall_type: OverallReportType",
+            i.specific_case AS "specific_case?: ReportItemType",
+            i.str_content AS "str_content?",
+            i.num_content AS "num_content?"
+        FROM (
+            SELECT DISTINCT path_verification
+            FROM observed_route
+            WHERE verification_run = $1 AND path_verification IS NOT NULL
+        ) o
+        JOIN path_verification v ON v.path_verification_id = o.path_verification
+        JOIN route_path p ON p.route_path_id = v.route_path_id
+        JOIN exchange_report e ON e.parent_verification = v.path_verification_id
+        LEFT JOIN report_item i ON i.parent_report = e.report_id
+        ORDER BY e.report_id"#,
+        run_id,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut reports: Vec<HopReport> = Vec::new();
+    let mut last_report_id = None;
+    for row in rows {
+        if last_report_id != Some(row.report_id) {
+            last_report_id = Some(row.report_id);
+            reports.push(HopReport {
+                route_path_id: row.route_path_id,
+                address_prefix: row.address_prefix,
+                as_path: row.as_path,
+                hop_index: row.hop_index,
+                from_as: row.from_as,
+                to_as: row.to_as,
+                import: row.import,
+                overall_type: row.overall_type,
+                items: Vec::new(),
+            });
+        }
+        if let Some(specific_case) = row.specific_case {
+            let report = reports.last_mut().expect("pushed above");
+            report.items.push(ReportItemRecord {
+                specific_case,
+                str_content: row.str_content,
+                num_content: row.num_content,
+            });
+        }
+    }
+    Ok(reports)
+}
+
+async fn insert_report_diff(
+    conn: &mut PgConnection,
+    old_run: i32,
+    new_run: i32,
+    diff: &HopDiff,
+) -> Result<i32> {
+    let report_diff_id = sqlx::query!(
+        r#"INSERT INTO report_diff(old_run, new_run, route_path_id, hop_index, from_as, to_as, import, old_type, new_type, added_items, removed_items)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING report_diff_id"#,
+        old_run,
+        new_run,
+        diff.route_path_id,
+        diff.hop_index,
+        diff.from_as,
+        diff.to_as,
+        diff.import,
+        diff.old_type as _,
+        diff.new_type as _,
+        serde_json::to_value(&diff.added_items)?,
+        serde_json::to_value(&diff.removed_items)?,
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .report_diff_id;
+    Ok(report_diff_id)
+}
+
+fn write_csv(writer: &mut impl Write, diffs: &[HopDiff]) -> Result<()> {
+    writeln!(
+        writer,
+        "route_path_id,address_prefix,as_path,hop_index,from_as,to_as,import,old_type,new_type,added_items,removed_items"
+    )?;
+    let type_str = |overall_type: Option<OverallReportType>| {
+        overall_type.map_or("", OverallReportType::as_str)
+    };
+    for diff in diffs {
+        let fields = [
+            diff.route_path_id.to_string(),
+            diff.address_prefix.clone(),
+            diff.as_path.clone(),
+            diff.hop_index
+                .map(|hop| hop.to_string())
+                .unwrap_or_default(),
+            diff.from_as.to_string(),
+            diff.to_as.to_string(),
+            diff.import.to_string(),
+            type_str(diff.old_type).into(),
+            type_str(diff.new_type).into(),
+            serde_json::to_string(&diff.added_items)?,
+            serde_json::to_string(&diff.removed_items)?,
+        ];
+        let fields: Vec<_> = fields.iter().map(|field| csv_field(field)).collect();
+        writeln!(writer, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+/// Quote `field` if it contains characters special to CSV.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hop(
+        route_path_id: i32,
+        hop_index: i32,
+        overall_type: OverallReportType,
+        items: &[ReportItemType],
+    ) -> HopReport {
+        HopReport {
+            route_path_id,
+            address_prefix: "203.0.113.0/24".parse().unwrap(),
+            as_path: "64496 13335".into(),
+            hop_index: Some(hop_index),
+            from_as: 13335,
+            to_as: 64496,
+            import: true,
+            overall_type,
+            items: items
+                .iter()
+                .map(|&specific_case| ReportItemRecord {
+                    specific_case,
+                    str_content: None,
+                    num_content: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn changed_hops() {
+        use OverallReportType::*;
+        use ReportItemType::*;
+        let old = [
+            hop(1, 1, Bad, &[ErrFilter]),
+            hop(2, 1, Ok, &[]),
+            // Route path only in the old run.
+            hop(3, 1, Bad, &[ErrPeering]),
+        ];
+        let new = [
+            hop(1, 1, Ok, &[]),
+            hop(2, 1, Ok, &[]),
+            hop(2, 2, Unrecorded, &[UnrecAutNum]),
+        ];
+        let diffs = diff_hops(&old, &new);
+        assert_eq!(diffs.len(), 2);
+
+        assert_eq!(
+            (diffs[0].old_type, diffs[0].new_type),
+            (Some(Bad), Some(Ok))
+        );
+        assert!(diffs[0].added_items.is_empty());
+        assert_eq!(diffs[0].removed_items[0].specific_case, ErrFilter);
+
+        assert_eq!(diffs[1].route_path_id, 2);
+        assert_eq!(
+            (diffs[1].old_type, diffs[1].new_type),
+            (None, Some(Unrecorded))
+        );
+        assert_eq!(diffs[1].added_items[0].specific_case, UnrecAutNum);
+    }
+
+    #[test]
+    fn repeated_items() {
+        use OverallReportType::*;
+        use ReportItemType::*;
+        let old = [hop(1, 1, Bad, &[ErrFilter, ErrPeering])];
+        let new = [hop(1, 1, Bad, &[ErrPeering, ErrFilter, ErrFilter])];
+        let diffs = diff_hops(&old, &new);
+        assert_eq!(diffs.len(), 1);
+        let added: Vec<_> = diffs[0]
+            .added_items
+            .iter()
+            .map(|item| item.specific_case)
+            .collect();
+        assert_eq!(added, [ErrFilter]);
+        assert!(diffs[0].removed_items.is_empty());
+
+        // Reordered items are the same multiset.
+        let reordered = [hop(1, 1, Bad, &[ErrPeering, ErrFilter])];
+        assert!(diff_hops(&old, &reordered).is_empty());
+    }
+
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(csv_field("64496 13335"), "64496 13335");
+        assert_eq!(csv_field(r#"[{"a":1,"b":2}]"#), r#""[{""a"":1,""b"":2}]""#);
+    }
+}
@@ -0,0 +1,227 @@
+//! AS hierarchy of an AS relationship snapshot:
+//! each AS's customer cone and its depth below the tier-1 clique.
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet, VecDeque};
+
+use anyhow::Result;
+use log::info;
+use sqlx::{Pool, Postgres};
+
+/// Place of an AS in the hierarchy of a snapshot.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AsHierarchy {
+    /// Number of ASes in the customer cone, including the AS itself.
+    pub cone_size: i32,
+    /// Provider-to-customer hops from the nearest tier-1 AS,
+    /// `None` if no provider chain reaches the clique.
+    pub clique_depth: Option<i32>,
+}
+
+/// Customers of each provider.
+pub type Customers = BTreeMap<i32, Vec<i32>>;
+
+/// Customer cone of every AS in `ases`:
+/// the AS itself and every AS reachable by going down to customers.
+pub fn customer_cones(ases: &BTreeSet<i32>, customers: &Customers) -> BTreeMap<i32, BTreeSet<i32>> {
+    ases.iter()
+        .map(|&as_num| {
+            let mut cone = BTreeSet::from([as_num]);
+            let mut stack = vec![as_num];
+            while let Some(provider) = stack.pop() {
+                for &customer in customers.get(&provider).into_iter().flatten() {
+                    if cone.insert(customer) {
+                        stack.push(customer);
+                    }
+                }
+            }
+            (as_num, cone)
+        })
+        .collect()
+}
+
+/// Fewest provider-to-customer hops from `clique` to each AS reachable from it.
+pub fn clique_depths(clique: &BTreeSet<i32>, customers: &Customers) -> BTreeMap<i32, i32> {
+    let mut depths: BTreeMap<_, _> = clique.iter().map(|&as_num| (as_num, 0)).collect();
+    let mut queue: VecDeque<_> = clique.iter().copied().collect();
+    while let Some(provider) = queue.pop_front() {
+        let depth = depths[&provider] + 1;
+        for &customer in customers.get(&provider).into_iter().flatten() {
+            if let Entry::Vacant(entry) = depths.entry(customer) {
+                entry.insert(depth);
+                queue.push_back(customer);
+            }
+        }
+    }
+    depths
+}
+
+/// Compute and store the customer cone and clique depth of every AS
+/// in AS relationship source `as_rel_source`,
+/// replacing those computed before.
+pub async fn compute_hierarchy(pool: &Pool<Postgres>, as_rel_source: i32) -> Result<()> {
+    let mut customers = Customers::new();
+    let mut ases = BTreeSet::new();
+    for row in sqlx::query!(
+        "SELECT provider, customer FROM provide_customer WHERE as_rel_source = $1",
+        as_rel_source
+    )
+    .fetch_all(pool)
+    .await?
+    {
+        customers
+            .entry(row.provider)
+            .or_default()
+            .push(row.customer);
+        ases.extend([row.provider, row.customer]);
+    }
+    for row in sqlx::query!(
+        "SELECT peer_1, peer_2 FROM peer WHERE as_rel_source = $1",
+        as_rel_source
+    )
+    .fetch_all(pool)
+    .await?
+    {
+        ases.extend([row.peer_1, row.peer_2]);
+    }
+    let clique: BTreeSet<_> = sqlx::query_scalar!(
+        "SELECT as_num FROM tier1 WHERE as_rel_source = $1",
+        as_rel_source
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect();
+    ases.extend(&clique);
+
+    let cones = customer_cones(&ases, &customers);
+    let depths = clique_depths(&clique, &customers);
+
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        "DELETE FROM customer_cone WHERE as_rel_source = $1",
+        as_rel_source
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM as_hierarchy WHERE as_rel_source = $1",
+        as_rel_source
+    )
+    .execute(&mut *transaction)
+    .await?;
+    for (as_num, cone) in &cones {
+        let members: Vec<_> = cone.iter().copied().collect();
+        sqlx::query!(
+            "INSERT INTO as_hierarchy(as_rel_source, as_num, cone_size, clique_depth) VALUES ($1, $2, $3, $4)",
+            as_rel_source,
+            as_num,
+            members.len() as i32,
+            depths.get(as_num),
+        )
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO customer_cone(as_rel_source, as_num, member) SELECT $1, $2, unnest($3::int[])",
+            as_rel_source,
+            as_num,
+            &members,
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+    transaction.commit().await?;
+
+    info!(
+        "Computed the hierarchy of {} ASes, {} of them reaching the clique, for AS relationship source {as_rel_source}.",
+        cones.len(),
+        depths.len()
+    );
+    Ok(())
+}
+
+/// Hierarchy of `as_num` in the latest snapshot.
+/// `None` if the AS is not in it.
+pub async fn as_hierarchy(pool: &Pool<Postgres>, as_num: u32) -> Result<Option<AsHierarchy>> {
+    let row = sqlx::query!(
+        r#"SELECT cone_size, clique_depth FROM as_hierarchy
+        WHERE as_num = $1 AND as_rel_source = (
+            SELECT as_rel_source_id FROM as_rel_source
+            ORDER BY snapshot_date DESC, as_rel_source_id DESC
+            LIMIT 1
+        )"#,
+        as_num as i32
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|row| AsHierarchy {
+        cone_size: row.cone_size,
+        clique_depth: row.clique_depth,
+    }))
+}
+
+/// Customer cone of `as_num` in the latest snapshot, including itself.
+/// Empty if the AS is not in it.
+pub async fn customer_cone(pool: &Pool<Postgres>, as_num: u32) -> Result<Vec<u32>> {
+    let members = sqlx::query_scalar!(
+        r#"SELECT member FROM customer_cone
+        WHERE as_num = $1 AND as_rel_source = (
+            SELECT as_rel_source_id FROM as_rel_source
+            ORDER BY snapshot_date DESC, as_rel_source_id DESC
+            LIMIT 1
+        )"#,
+        as_num as i32
+    )
+    .fetch_all(pool)
+    .await?;
+    // Sorted unsigned, since 32-bit ASNs wrap around in `int`.
+    let mut members: Vec<_> = members.into_iter().map(|member| member as u32).collect();
+    members.sort_unstable();
+    Ok(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Executor, PgPool};
+
+    use super::*;
+    use crate::as_rel::as_relationship_db;
+
+    #[test]
+    fn cones_and_depths() {
+        // 174 and 3356 form the clique; 64496 is multihomed to both,
+        // 64497 sits under 64496, and 64498 only peers.
+        let customers = Customers::from([
+            (174, vec![64496]),
+            (3356, vec![64496, 64499]),
+            (64496, vec![64497]),
+            // Relationship cycle.
+            (64497, vec![64496]),
+        ]);
+        let ases = BTreeSet::from([174, 3356, 64496, 64497, 64498, 64499]);
+        let cones = customer_cones(&ases, &customers);
+        assert_eq!(cones[&3356], BTreeSet::from([3356, 64496, 64497, 64499]));
+        assert_eq!(cones[&64496], BTreeSet::from([64496, 64497]));
+        assert_eq!(cones[&64498], BTreeSet::from([64498]));
+
+        let depths = clique_depths(&BTreeSet::from([174, 3356]), &customers);
+        assert_eq!(depths[&174], 0);
+        assert_eq!(depths[&64496], 1);
+        assert_eq!(depths[&64497], 2);
+        assert_eq!(depths.get(&64498), None);
+    }
+
+    #[sqlx::test]
+    async fn stored_cones(pool: PgPool) -> Result<()> {
+        pool.execute(include_str!("../../demo_v1.sql")).await?;
+        pool.execute(include_str!("../../trigger_only.sql")).await?;
+        as_relationship_db(&pool, "fixtures/20230701.as-rel.bz2").await?;
+
+        assert_eq!(
+            customer_cone(&pool, 3356).await?,
+            [3356, 4_200_000_000, 4_200_000_001]
+        );
+        let hierarchy = as_hierarchy(&pool, 4_200_000_000).await?.unwrap();
+        assert_eq!(hierarchy.cone_size, 2);
+        assert_eq!(hierarchy.clique_depth, Some(1));
+        Ok(())
+    }
+}
@@ -0,0 +1,777 @@
+//! Native reader for MRT files (RFC 6396), so `record` does not need
+//! `bgpdump`.
+//! Decodes TABLE_DUMP_V2 RIB entries and BGP4MP UPDATE messages into the
+//! lines `bgpdump -m` prints for them.
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::Path,
+};
+
+use anyhow::{bail, ensure, Result};
+use bzip2::read::MultiBzDecoder;
+use log::debug;
+use sqlx::types::ipnetwork::IpNetwork;
+
+// MRT types.
+const TABLE_DUMP_V2: u16 = 13;
+const BGP4MP: u16 = 16;
+const BGP4MP_ET: u16 = 17;
+
+// TABLE_DUMP_V2 subtypes.
+const PEER_INDEX_TABLE: u16 = 1;
+const RIB_IPV4_UNICAST: u16 = 2;
+const RIB_IPV4_MULTICAST: u16 = 3;
+const RIB_IPV6_UNICAST: u16 = 4;
+const RIB_IPV6_MULTICAST: u16 = 5;
+const RIB_IPV4_UNICAST_ADDPATH: u16 = 8;
+const RIB_IPV4_MULTICAST_ADDPATH: u16 = 9;
+const RIB_IPV6_UNICAST_ADDPATH: u16 = 10;
+const RIB_IPV6_MULTICAST_ADDPATH: u16 = 11;
+
+// BGP4MP subtypes.
+const BGP4MP_MESSAGE: u16 = 1;
+const BGP4MP_MESSAGE_AS4: u16 = 4;
+const BGP4MP_MESSAGE_LOCAL: u16 = 6;
+const BGP4MP_MESSAGE_AS4_LOCAL: u16 = 7;
+
+const BGP_UPDATE: u8 = 2;
+const AFI_IPV4: u16 = 1;
+const AFI_IPV6: u16 = 2;
+const AS_TRANS: u32 = 23456;
+
+// BGP path attribute types.
+const ORIGIN: u8 = 1;
+const AS_PATH: u8 = 2;
+const NEXT_HOP: u8 = 3;
+const MULTI_EXIT_DISC: u8 = 4;
+const LOCAL_PREF: u8 = 5;
+const ATOMIC_AGGREGATE: u8 = 6;
+const AGGREGATOR: u8 = 7;
+const COMMUNITY: u8 = 8;
+const MP_REACH_NLRI: u8 = 14;
+const MP_UNREACH_NLRI: u8 = 15;
+const AS4_PATH: u8 = 17;
+const AS4_AGGREGATOR: u8 = 18;
+const LARGE_COMMUNITY: u8 = 32;
+
+/// A route announced or withdrawn in an MRT file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MrtEntry {
+    /// What `bgpdump -m` prints for the entry, including the newline.
+    pub raw: String,
+    pub timestamp: u32,
+    pub peer_ip: IpAddr,
+    pub peer_as: u32,
+    pub prefix: IpNetwork,
+    /// AS path as `bgpdump` prints it. `None` for withdrawals.
+    pub as_path: Option<String>,
+}
+
+/// Open the MRT file at `path`, decompressing it if it ends in `.bz2`.
+/// Uncompressed files start from the record at byte `offset` if given,
+/// and track the offset of each record; `.bz2` streams cannot seek.
+pub fn open_mrt<P: AsRef<Path>>(
+    path: P,
+    offset: Option<u64>,
+) -> Result<MrtReader<Box<dyn Read + Send>>> {
+    let path = path.as_ref();
+    let mut file = File::open(path)?;
+    if path.extension().is_some_and(|extension| extension == "bz2") {
+        let reader = BufReader::new(MultiBzDecoder::new(BufReader::new(file)));
+        return Ok(MrtReader::new(Box::new(reader)));
+    }
+
+    let offset = offset.unwrap_or(0);
+    let mut peers = Vec::new();
+    if offset > 0 {
+        // RIB entries refer to the peer index table at the start of the file.
+        peers = MrtReader::new(BufReader::new(&mut file)).peer_index()?;
+        file.seek(SeekFrom::Start(offset))?;
+    }
+    let mut reader = MrtReader::new(Box::new(BufReader::new(file)) as Box<dyn Read + Send>);
+    reader.peers = peers;
+    reader.position = Some(offset);
+    reader.record_offset = Some(offset);
+    Ok(reader)
+}
+
+/// Iterator over the [`MrtEntry`]s in an MRT stream,
+/// decoding one MRT record at a time.
+pub struct MrtReader<R> {
+    reader: R,
+    peers: Vec<Peer>,
+    pending: VecDeque<MrtEntry>,
+    done: bool,
+    /// Byte offset of the next record, if tracked.
+    position: Option<u64>,
+    /// Byte offset of the record the pending entries came from, if tracked.
+    record_offset: Option<u64>,
+}
+
+/// Common header and body of an MRT record.
+struct Record {
+    timestamp: u32,
+    mrt_type: u16,
+    subtype: u16,
+    body: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Peer {
+    ip: IpAddr,
+    asn: u32,
+}
+
+impl<R: Read> MrtReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            peers: Vec::new(),
+            pending: VecDeque::new(),
+            done: false,
+            position: None,
+            record_offset: None,
+        }
+    }
+
+    /// Byte offset of the record the last entry came from,
+    /// or where reading starts before the first entry.
+    /// `None` unless opened by [`open_mrt`] on an uncompressed file.
+    pub fn offset(&self) -> Option<u64> {
+        self.record_offset
+    }
+
+    /// Peers in the first record if it is a TABLE_DUMP_V2 peer index table.
+    fn peer_index(&mut self) -> Result<Vec<Peer>> {
+        match self.read_record()? {
+            Some(record)
+                if (record.mrt_type, record.subtype) == (TABLE_DUMP_V2, PEER_INDEX_TABLE) =>
+            {
+                peer_index_table(&mut Bytes(&record.body))
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Read the next MRT record.
+    /// Return `None` at the end of the stream.
+    fn read_record(&mut self) -> io::Result<Option<Record>> {
+        let mut header = [0; 12];
+        match self.reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(why) if why.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(why) => return Err(why),
+        }
+        let timestamp = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let mrt_type = u16::from_be_bytes([header[4], header[5]]);
+        let subtype = u16::from_be_bytes([header[6], header[7]]);
+        let length = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+        // Grow the body as it is read instead of trusting `length` up front,
+        // so a corrupt header cannot allocate gigabytes.
+        let mut body = Vec::new();
+        (&mut self.reader)
+            .take(length.into())
+            .read_to_end(&mut body)?;
+        if body.len() < length as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "MRT record of {length} bytes truncated after {}",
+                    body.len()
+                ),
+            ));
+        }
+        if let Some(position) = &mut self.position {
+            *position += header.len() as u64 + u64::from(length);
+        }
+        Ok(Some(Record {
+            timestamp,
+            mrt_type,
+            subtype,
+            body,
+        }))
+    }
+
+    /// Decode an MRT record into `self.pending`.
+    fn decode_record(&mut self, record: &Record) -> Result<()> {
+        let Record {
+            timestamp,
+            mrt_type,
+            subtype,
+            ref body,
+        } = *record;
+        let mut body = Bytes(body);
+        match (mrt_type, subtype) {
+            (TABLE_DUMP_V2, PEER_INDEX_TABLE) => self.peers = peer_index_table(&mut body)?,
+            (TABLE_DUMP_V2, RIB_IPV4_UNICAST | RIB_IPV4_MULTICAST) => {
+                self.rib(timestamp, false, false, &mut body)?
+            }
+            (TABLE_DUMP_V2, RIB_IPV6_UNICAST | RIB_IPV6_MULTICAST) => {
+                self.rib(timestamp, true, false, &mut body)?
+            }
+            (TABLE_DUMP_V2, RIB_IPV4_UNICAST_ADDPATH | RIB_IPV4_MULTICAST_ADDPATH) => {
+                self.rib(timestamp, false, true, &mut body)?
+            }
+            (TABLE_DUMP_V2, RIB_IPV6_UNICAST_ADDPATH | RIB_IPV6_MULTICAST_ADDPATH) => {
+                self.rib(timestamp, true, true, &mut body)?
+            }
+            (BGP4MP | BGP4MP_ET, _) => {
+                let head = match mrt_type {
+                    BGP4MP_ET => format!("BGP4MP_ET|{timestamp}.{:06}", body.u32()?),
+                    _ => format!("BGP4MP|{timestamp}"),
+                };
+                match subtype {
+                    BGP4MP_MESSAGE | BGP4MP_MESSAGE_LOCAL => {
+                        self.bgp4mp_message(&head, timestamp, false, &mut body)?
+                    }
+                    BGP4MP_MESSAGE_AS4 | BGP4MP_MESSAGE_AS4_LOCAL => {
+                        self.bgp4mp_message(&head, timestamp, true, &mut body)?
+                    }
+                    _ => debug!("Skipping BGP4MP subtype {subtype}."),
+                }
+            }
+            _ => debug!("Skipping MRT type {mrt_type} subtype {subtype}."),
+        }
+        Ok(())
+    }
+
+    fn rib(&mut self, timestamp: u32, ipv6: bool, add_path: bool, body: &mut Bytes) -> Result<()> {
+        _ = body.u32()?; // Sequence number.
+        let prefix = body.prefix(ipv6)?;
+        let n_entry = body.u16()?;
+        for _ in 0..n_entry {
+            let peer_index = body.u16()? as usize;
+            _ = body.u32()?; // Originated time.
+            if add_path {
+                _ = body.u32()?; // Path identifier.
+            }
+            let attributes_len = body.u16()? as usize;
+            let attributes =
+                Attributes::decode(&mut Bytes(body.take(attributes_len)?), true, true)?;
+            let Some(peer) = self.peers.get(peer_index) else {
+                bail!("RIB entry for {prefix} refers to unknown peer {peer_index}");
+            };
+            let head = format!("TABLE_DUMP2|{timestamp}|B");
+            self.pending
+                .push_back(announcement(&head, timestamp, peer, prefix, &attributes));
+        }
+        Ok(())
+    }
+
+    fn bgp4mp_message(
+        &mut self,
+        head: &str,
+        timestamp: u32,
+        as4: bool,
+        body: &mut Bytes,
+    ) -> Result<()> {
+        let peer_as = body.asn(as4)?;
+        _ = body.asn(as4)?; // Local AS.
+        _ = body.u16()?; // Interface index.
+        let ipv6 = match body.u16()? {
+            AFI_IPV4 => false,
+            AFI_IPV6 => true,
+            afi => bail!("Unknown BGP4MP address family {afi}"),
+        };
+        let peer = Peer {
+            ip: body.ip(ipv6)?,
+            asn: peer_as,
+        };
+        _ = body.ip(ipv6)?; // Local IP.
+
+        _ = body.take(16)?; // Marker.
+        _ = body.u16()?; // Length.
+        if body.u8()? != BGP_UPDATE {
+            return Ok(());
+        }
+        let withdrawn_len = body.u16()? as usize;
+        let mut withdrawn = Bytes(body.take(withdrawn_len)?);
+        let attributes_len = body.u16()? as usize;
+        let attributes = Attributes::decode(&mut Bytes(body.take(attributes_len)?), as4, false)?;
+
+        let withdraw = |prefix: IpNetwork| MrtEntry {
+            raw: format!("{head}|W|{}|{}|{prefix}\n", peer.ip, peer.asn),
+            timestamp,
+            peer_ip: peer.ip,
+            peer_as: peer.asn,
+            prefix,
+            as_path: None,
+        };
+        while !withdrawn.is_empty() {
+            self.pending.push_back(withdraw(withdrawn.prefix(false)?));
+        }
+        for prefix in &attributes.mp_unreach {
+            self.pending.push_back(withdraw(*prefix));
+        }
+        let head = format!("{head}|A");
+        while !body.is_empty() {
+            let prefix = body.prefix(false)?;
+            self.pending
+                .push_back(announcement(&head, timestamp, &peer, prefix, &attributes));
+        }
+        for prefix in &attributes.mp_reach {
+            self.pending
+                .push_back(announcement(&head, timestamp, &peer, *prefix, &attributes));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for MrtReader<R> {
+    type Item = Result<MrtEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.pending.pop_front() {
+                return Some(Ok(entry));
+            }
+            if self.done {
+                return None;
+            }
+            self.record_offset = self.position;
+            match self.read_record() {
+                Ok(Some(record)) => {
+                    if let Err(why) = self.decode_record(&record) {
+                        return Some(Err(why));
+                    }
+                }
+                Ok(None) => self.done = true,
+                Err(why) => {
+                    self.done = true;
+                    return Some(Err(why.into()));
+                }
+            }
+        }
+    }
+}
+
+fn peer_index_table(body: &mut Bytes) -> Result<Vec<Peer>> {
+    _ = body.u32()?; // Collector BGP ID.
+    let view_name_len = body.u16()? as usize;
+    _ = body.take(view_name_len)?;
+    let n_peer = body.u16()?;
+    (0..n_peer)
+        .map(|_| {
+            let peer_type = body.u8()?;
+            _ = body.u32()?; // Peer BGP ID.
+            let ip = body.ip(peer_type & 1 != 0)?;
+            let asn = body.asn(peer_type & 2 != 0)?;
+            Ok(Peer { ip, asn })
+        })
+        .collect()
+}
+
+/// Format an announcement of `prefix` the way `bgpdump -m` does.
+/// `head` holds the record type, time, and entry type fields.
+fn announcement(
+    head: &str,
+    timestamp: u32,
+    peer: &Peer,
+    prefix: IpNetwork,
+    attributes: &Attributes,
+) -> MrtEntry {
+    let as_path = attributes.as_path_str();
+    let origin = match attributes.origin {
+        Some(0) => "IGP",
+        Some(1) => "EGP",
+        Some(2) => "INCOMPLETE",
+        _ => "",
+    };
+    let next_hop = match prefix {
+        IpNetwork::V4(_) => attributes.next_hop.or(attributes.mp_next_hop),
+        IpNetwork::V6(_) => attributes.mp_next_hop.or(attributes.next_hop),
+    }
+    .map(|ip| ip.to_string())
+    .unwrap_or_default();
+    let communities = attributes
+        .communities
+        .iter()
+        .map(|&community| community_str(community))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let atomic_aggregate = match attributes.atomic_aggregate {
+        true => "AG",
+        false => "NAG",
+    };
+    let aggregator = attributes
+        .aggregator
+        .map(|(asn, ip)| format!("{asn} {ip}"))
+        .unwrap_or_default();
+    let large_communities = attributes
+        .large_communities
+        .iter()
+        .map(|(admin, data1, data2)| format!("{admin}:{data1}:{data2}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let raw = format!(
+        "{head}|{}|{}|{prefix}|{as_path}|{origin}|{next_hop}|{}|{}|{communities}|{atomic_aggregate}|{aggregator}|{large_communities}|\n",
+        peer.ip,
+        peer.asn,
+        attributes.local_pref.unwrap_or(0),
+        attributes.med.unwrap_or(0),
+    );
+    MrtEntry {
+        raw,
+        timestamp,
+        peer_ip: peer.ip,
+        peer_as: peer.asn,
+        prefix,
+        as_path: Some(as_path),
+    }
+}
+
+fn community_str(community: u32) -> String {
+    match community {
+        0 => "internet".into(),
+        0xFFFFFF01 => "no-export".into(),
+        0xFFFFFF02 => "no-advertise".into(),
+        0xFFFFFF03 => "local-AS".into(),
+        _ => format!("{}:{}", community >> 16, community & 0xFFFF),
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Segment {
+    Set(Vec<u32>),
+    Sequence(Vec<u32>),
+    ConfedSequence(Vec<u32>),
+    ConfedSet(Vec<u32>),
+}
+
+impl Segment {
+    /// Length the segment adds to the AS path, per RFC 4271.
+    fn path_len(&self) -> usize {
+        match self {
+            Segment::Set(_) => 1,
+            Segment::Sequence(nums) => nums.len(),
+            Segment::ConfedSequence(_) | Segment::ConfedSet(_) => 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Attributes {
+    origin: Option<u8>,
+    as_path: Vec<Segment>,
+    next_hop: Option<IpAddr>,
+    med: Option<u32>,
+    local_pref: Option<u32>,
+    atomic_aggregate: bool,
+    aggregator: Option<(u32, Ipv4Addr)>,
+    communities: Vec<u32>,
+    large_communities: Vec<(u32, u32, u32)>,
+    mp_next_hop: Option<IpAddr>,
+    mp_reach: Vec<IpNetwork>,
+    mp_unreach: Vec<IpNetwork>,
+}
+
+impl Attributes {
+    /// Decode path attributes.
+    /// `as4` tells whether AS numbers take four bytes,
+    /// `rib` whether MP_REACH_NLRI may be in the abbreviated form of RIB
+    /// entries.
+    fn decode(bytes: &mut Bytes, as4: bool, rib: bool) -> Result<Self> {
+        let mut attributes = Self::default();
+        let mut as4_path = None;
+        let mut as4_aggregator = None;
+        while !bytes.is_empty() {
+            let flags = bytes.u8()?;
+            let attribute_type = bytes.u8()?;
+            let len = match flags & 0x10 {
+                0 => bytes.u8()? as usize,
+                _ => bytes.u16()? as usize,
+            };
+            let mut value = Bytes(bytes.take(len)?);
+            match attribute_type {
+                ORIGIN => attributes.origin = Some(value.u8()?),
+                AS_PATH => attributes.as_path = as_path(&mut value, as4)?,
+                NEXT_HOP => attributes.next_hop = Some(IpAddr::V4(value.ipv4()?)),
+                MULTI_EXIT_DISC => attributes.med = Some(value.u32()?),
+                LOCAL_PREF => attributes.local_pref = Some(value.u32()?),
+                ATOMIC_AGGREGATE => attributes.atomic_aggregate = true,
+                AGGREGATOR => attributes.aggregator = Some((value.asn(as4)?, value.ipv4()?)),
+                COMMUNITY => {
+                    while !value.is_empty() {
+                        attributes.communities.push(value.u32()?);
+                    }
+                }
+                MP_REACH_NLRI => attributes.mp_reach(&mut value, rib)?,
+                MP_UNREACH_NLRI => {
+                    let ipv6 = value.u16()? == AFI_IPV6;
+                    _ = value.u8()?; // SAFI.
+                    while !value.is_empty() {
+                        attributes.mp_unreach.push(value.prefix(ipv6)?);
+                    }
+                }
+                AS4_PATH => as4_path = Some(as_path(&mut value, true)?),
+                AS4_AGGREGATOR => as4_aggregator = Some((value.u32()?, value.ipv4()?)),
+                LARGE_COMMUNITY => {
+                    while !value.is_empty() {
+                        let community = (value.u32()?, value.u32()?, value.u32()?);
+                        attributes.large_communities.push(community);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if !as4 {
+            if let Some(as4_path) = as4_path {
+                attributes.as_path = merge_as4_path(attributes.as_path, as4_path);
+            }
+            if let (Some((AS_TRANS, _)), Some(as4_aggregator)) =
+                (attributes.aggregator, as4_aggregator)
+            {
+                attributes.aggregator = Some(as4_aggregator);
+            }
+        }
+        Ok(attributes)
+    }
+
+    fn mp_reach(&mut self, value: &mut Bytes, rib: bool) -> Result<()> {
+        // RIB entries may only keep the next hop length and address.
+        let abbreviated =
+            rib && value.0.first().map(|&len| len as usize + 1) == Some(value.0.len());
+        let ipv6 = if abbreviated {
+            None
+        } else {
+            let ipv6 = value.u16()? == AFI_IPV6;
+            _ = value.u8()?; // SAFI.
+            Some(ipv6)
+        };
+        let next_hop_len = value.u8()? as usize;
+        let next_hop = value.take(next_hop_len)?;
+        self.mp_next_hop = match next_hop_len {
+            4 => Some(IpAddr::V4(Bytes(next_hop).ipv4()?)),
+            // Global address, possibly followed by a link-local one.
+            16 | 32 => Some(IpAddr::V6(Bytes(next_hop).ipv6()?)),
+            _ => None,
+        };
+        if let Some(ipv6) = ipv6 {
+            _ = value.u8()?; // Reserved.
+            while !value.is_empty() {
+                self.mp_reach.push(value.prefix(ipv6)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn as_path_str(&self) -> String {
+        let join = |nums: &[u32], separator: &str| {
+            nums.iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(separator)
+        };
+        self.as_path
+            .iter()
+            .map(|segment| match segment {
+                Segment::Sequence(nums) => join(nums, " "),
+                Segment::Set(nums) => format!("{{{}}}", join(nums, ",")),
+                Segment::ConfedSequence(nums) => format!("({})", join(nums, " ")),
+                Segment::ConfedSet(nums) => format!("[{}]", join(nums, ",")),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn as_path(value: &mut Bytes, as4: bool) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    while !value.is_empty() {
+        let segment_type = value.u8()?;
+        let n_num = value.u8()?;
+        let nums = (0..n_num)
+            .map(|_| value.asn(as4))
+            .collect::<Result<Vec<_>>>()?;
+        segments.push(match segment_type {
+            1 => Segment::Set(nums),
+            2 => Segment::Sequence(nums),
+            3 => Segment::ConfedSequence(nums),
+            4 => Segment::ConfedSet(nums),
+            _ => bail!("Unknown AS path segment type {segment_type}"),
+        });
+    }
+    Ok(segments)
+}
+
+/// Reconstruct the AS path from a two-byte AS_PATH and AS4_PATH,
+/// per RFC 6793 section 4.2.3.
+fn merge_as4_path(as_path: Vec<Segment>, as4_path: Vec<Segment>) -> Vec<Segment> {
+    let path_len = |path: &[Segment]| path.iter().map(Segment::path_len).sum::<usize>();
+    let (len, len4) = (path_len(&as_path), path_len(&as4_path));
+    if len4 > len {
+        return as_path;
+    }
+    let mut keep = len - len4;
+    let mut merged = Vec::new();
+    for segment in as_path {
+        if keep == 0 {
+            break;
+        }
+        match segment {
+            Segment::Sequence(nums) => {
+                let n_take = nums.len().min(keep);
+                keep -= n_take;
+                merged.push(Segment::Sequence(nums[..n_take].to_vec()));
+            }
+            segment => {
+                keep -= segment.path_len();
+                merged.push(segment);
+            }
+        }
+    }
+    merged.extend(as4_path);
+    merged
+}
+
+/// Big-endian reader over an MRT record.
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Bytes<'a> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        ensure!(
+            self.0.len() >= n,
+            "MRT record truncated: need {n} bytes, {} left",
+            self.0.len()
+        );
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn asn(&mut self, as4: bool) -> Result<u32> {
+        match as4 {
+            true => self.u32(),
+            false => Ok(self.u16()? as u32),
+        }
+    }
+
+    fn ipv4(&mut self) -> Result<Ipv4Addr> {
+        Ok(Ipv4Addr::from(self.u32()?))
+    }
+
+    fn ipv6(&mut self) -> Result<Ipv6Addr> {
+        let bytes: [u8; 16] = self.take(16)?.try_into()?;
+        Ok(Ipv6Addr::from(bytes))
+    }
+
+    fn ip(&mut self, ipv6: bool) -> Result<IpAddr> {
+        Ok(match ipv6 {
+            true => IpAddr::V6(self.ipv6()?),
+            false => IpAddr::V4(self.ipv4()?),
+        })
+    }
+
+    /// Prefix encoded as its length in bits and just enough bytes.
+    fn prefix(&mut self, ipv6: bool) -> Result<IpNetwork> {
+        let len = self.u8()?;
+        let max_len = if ipv6 { 128 } else { 32 };
+        ensure!(len <= max_len, "Prefix length {len} exceeds {max_len}");
+        let bytes = self.take((len as usize).div_ceil(8))?;
+        let addr = match ipv6 {
+            true => {
+                let mut octets = [0; 16];
+                octets[..bytes.len()].copy_from_slice(bytes);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            false => {
+                let mut octets = [0; 4];
+                octets[..bytes.len()].copy_from_slice(bytes);
+                IpAddr::V4(Ipv4Addr::from(octets))
+            }
+        };
+        Ok(IpNetwork::new(addr, len)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_lines(path: &str) -> Vec<String> {
+        open_mrt(path, None)
+            .unwrap()
+            .map(|entry| entry.unwrap().raw)
+            .collect()
+    }
+
+    #[test]
+    fn table_dump_v2() {
+        assert_eq!(
+            raw_lines("fixtures/rib.mrt.bz2"),
+            [
+                "TABLE_DUMP2|1687212000|B|192.0.2.1|64496|203.0.113.0/24|64496 3356 3356 13335|IGP|192.0.2.1|0|10|3356:100 no-export|NAG|||\n",
+                "TABLE_DUMP2|1687212000|B|2001:db8::1|4200000001|203.0.113.0/24|4200000001 174 {65001,65002}|INCOMPLETE|192.0.2.2|100|0||AG|174 198.51.100.1|4200000001:1:2|\n",
+                "TABLE_DUMP2|1687212000|B|2001:db8::1|4200000001|2001:db8:1000::/36|4200000001 6939|IGP|2001:db8::1|0|0||NAG|||\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn bgp4mp_updates() {
+        assert_eq!(
+            raw_lines("fixtures/updates.mrt"),
+            [
+                "BGP4MP|1687212000|W|192.0.2.1|64496|198.51.100.0/24\n",
+                "BGP4MP|1687212000|A|192.0.2.1|64496|203.0.113.0/24|64496 13335|IGP|192.0.2.1|0|0||NAG|||\n",
+                "BGP4MP|1687212000|A|192.0.2.1|64496|203.0.113.128/25|64496 13335|IGP|192.0.2.1|0|0||NAG|||\n",
+                "BGP4MP|1687212001|A|192.0.2.5|64497|192.0.2.0/24|64497 4200000002 4200000003|EGP|192.0.2.5|0|0||NAG|4200000003 10.0.0.9||\n",
+                "BGP4MP_ET|1687212002.000123|W|2001:db8::1|4200000001|2001:db8:dead::/48\n",
+                "BGP4MP_ET|1687212002.000123|A|2001:db8::1|4200000001|2001:db8:beef::/48|4200000001 6939|IGP|2001:db8::1|0|0||NAG|||\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn withdrawals_have_no_as_path() {
+        let entries: Vec<_> = open_mrt("fixtures/updates.mrt", None)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        let withdrawn: Vec<_> = entries
+            .iter()
+            .filter(|entry| entry.as_path.is_none())
+            .map(|entry| entry.prefix.to_string())
+            .collect();
+        assert_eq!(withdrawn, ["198.51.100.0/24", "2001:db8:dead::/48"]);
+    }
+
+    #[test]
+    fn truncated_record_is_an_error() {
+        let updates = std::fs::read("fixtures/updates.mrt").unwrap();
+        let mut reader = MrtReader::new(&updates[..40]);
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn oversized_length_is_an_error() {
+        // BGP4MP header claiming a 4 GiB body.
+        let mut record = vec![0, 0, 0, 0, 0, 16, 0, 4, 0xff, 0xff, 0xff, 0xff];
+        record.extend([0; 16]);
+        let mut reader = MrtReader::new(&record[..]);
+        assert!(reader.next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,131 @@
+//! Aut-num import and export policies as rows, one per rule,
+//! so policies can be queried without walking the IR JSON,
+//! and the objects policies reference.
+//! [`crate::adapter`] flattens the upstream IR into these.
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use serde_json::Value;
+use sqlx::{postgres::PgConnection, Pool, Postgres};
+
+/// Rule of an `mp-import` or `mp-export` policy.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PolicyRule {
+    /// Position among the aut-num's import or export rules in the IR,
+    /// which groups rules by address family and cast.
+    pub rule_index: i32,
+    /// `any`, `ipv4`, or `ipv6`.
+    pub address_family: &'static str,
+    /// `any`, `unicast`, or `multicast`.
+    pub cast_type: &'static str,
+    /// IR of the `mp-filter`.
+    pub filter: Value,
+    pub peerings: Vec<PolicyPeering>,
+    /// Objects the rule's peerings and filter reference.
+    pub references: BTreeSet<PolicyReference>,
+}
+
+/// Peering a rule applies to, with the actions taken on it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PolicyPeering {
+    /// IR of each part of the `mp-peering`.
+    pub remote_as: Value,
+    pub remote_router: Option<Value>,
+    pub local_router: Option<Value>,
+    pub actions: Value,
+}
+
+/// Object referenced by a policy, defined or not.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct PolicyReference {
+    /// `as_num`, `as_set`, `route_set`, `filter_set`, or `peering_set`.
+    pub referenced_type: &'static str,
+    /// RPSL name of the object, e.g., `AS3356` or `AS-FOO`.
+    pub referenced_name: String,
+    /// Whether the reference is in a peering rather than a filter.
+    pub in_peering: bool,
+}
+
+/// Insert the import (if `import`) or export `rules` of aut-num `as_num`,
+/// whose RPSL object is `rpsl_obj_name`.
+pub async fn insert_policy_rules(
+    conn: &mut PgConnection,
+    rpsl_obj_name: &str,
+    as_num: i32,
+    import: bool,
+    rules: &[PolicyRule],
+) -> Result<()> {
+    for rule in rules {
+        let policy_rule_id = sqlx::query!(
+            r#"INSERT INTO policy_rule(as_num, import, rule_index, address_family, cast_type, filter)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING policy_rule_id"#,
+            as_num,
+            import,
+            rule.rule_index,
+            rule.address_family,
+            rule.cast_type,
+            rule.filter,
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .policy_rule_id;
+
+        for (peering_index, peering) in rule.peerings.iter().enumerate() {
+            sqlx::query!(
+                r#"INSERT INTO policy_peering(policy_rule, peering_index, remote_as, remote_router, local_router, actions)
+                VALUES ($1, $2, $3, $4, $5, $6)"#,
+                policy_rule_id,
+                peering_index as i32,
+                peering.remote_as,
+                peering.remote_router,
+                peering.local_router,
+                peering.actions,
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+        insert_policy_references(
+            &mut *conn,
+            rpsl_obj_name,
+            Some(policy_rule_id),
+            &rule.references,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Insert the `references` of the filter-set or peering-set `rpsl_obj_name`.
+pub async fn insert_set_references(
+    pool: &Pool<Postgres>,
+    rpsl_obj_name: &str,
+    references: &BTreeSet<PolicyReference>,
+) -> Result<()> {
+    let mut transaction = pool.begin().await?;
+    insert_policy_references(&mut transaction, rpsl_obj_name, None, references).await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+async fn insert_policy_references(
+    conn: &mut PgConnection,
+    rpsl_obj_name: &str,
+    policy_rule: Option<i32>,
+    references: &BTreeSet<PolicyReference>,
+) -> sqlx::Result<()> {
+    for reference in references {
+        sqlx::query!(
+            r#"INSERT INTO policy_reference(rpsl_obj_name, policy_rule, in_peering, referenced_type, referenced_name)
+            VALUES ($1, $2, $3, $4, $5)"#,
+            rpsl_obj_name,
+            policy_rule,
+            reference.in_peering,
+            reference.referenced_type,
+            reference.referenced_name,
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
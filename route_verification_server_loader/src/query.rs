@@ -0,0 +1,1116 @@
+//! Typed queries behind the REST API server's endpoints,
+//! one function per endpoint of `flask_RestAPI/app.py`.
+//! Timestamps are returned as Postgres prints them,
+//! and address prefixes in CIDR notation.
+//! AS numbers are stored wrapped into `int`
+//! and serialized back as the unsigned numbers they are.
+//!
+//! Report details are paged by keyset on `(recorded_time, report_id)`
+//! instead of `OFFSET`, so deep pages cost as much as the first:
+//! each [`Page`] holds whole reports and a [`Cursor`] past the last one.
+//! Reports listed with the routes they were observed on are paged by
+//! `(recorded_time, report_id, observed_route_id)` instead,
+//! since many routes can share one report.
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use sqlx::{types::ipnetwork::IpNetwork, Pool, Postgres};
+
+use crate::report_type::{OverallReportType, ReportItemType};
+
+/// Reports per page unless the client asks for another number.
+pub const PAGE_SIZE: i64 = 10;
+/// Most reports per page a client may ask for.
+pub const MAX_PAGE_SIZE: i64 = 1000;
+/// Reports counted exactly before [`Count`] falls back to the planner's estimate.
+pub const EXACT_COUNT_LIMIT: i64 = 10_000;
+
+/// Position right after a report in `(recorded_time, report_id)` order,
+/// or after one of its observed routes.
+/// Clients pass it around as the opaque string of [`Cursor::encode`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cursor {
+    /// `recorded_time` in microseconds since the Unix epoch.
+    pub recorded_micros: i64,
+    pub report_id: i32,
+    /// `None` past every route the report was observed on.
+    pub observed_route_id: Option<i32>,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let raw = match self.observed_route_id {
+            Some(observed_route_id) => format!(
+                "{}.{}.{observed_route_id}",
+                self.recorded_micros, self.report_id
+            ),
+            None => format!("{}.{}", self.recorded_micros, self.report_id),
+        };
+        hex::encode(raw)
+    }
+
+    /// `None` if `cursor` did not come from [`Cursor::encode`].
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let raw = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+        let mut parts = raw.split('.');
+        let recorded_micros = parts.next()?.parse().ok()?;
+        let report_id = parts.next()?.parse().ok()?;
+        let observed_route_id = match parts.next() {
+            Some(observed_route_id) => Some(observed_route_id.parse().ok()?),
+            None => None,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            recorded_micros,
+            report_id,
+            observed_route_id,
+        })
+    }
+}
+
+/// Number of reports matched across all pages.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub struct Count {
+    pub count: i64,
+    /// Whether `count` was counted rather than estimated,
+    /// which happens beyond [`EXACT_COUNT_LIMIT`].
+    pub exact: bool,
+}
+
+/// Rows of consecutive reports, or of consecutive reports and routes.
+#[derive(Clone, Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// [`Cursor`] to get the next page with, `None` on the last page.
+    pub next_cursor: Option<String>,
+    pub total_reports: Count,
+}
+
+impl<T> Page<T> {
+    /// Page of `items` whose keys are `keys`, full if there are `limit`.
+    fn new(items: Vec<T>, keys: &[Cursor], limit: i64, total_reports: Count) -> Self {
+        let next_cursor = match keys.len() as i64 == limit {
+            true => keys.last().map(Cursor::encode),
+            false => None,
+        };
+        Self {
+            items,
+            next_cursor,
+            total_reports,
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            items: Vec::new(),
+            next_cursor: None,
+            total_reports: Count {
+                count: 0,
+                exact: true,
+            },
+        }
+    }
+}
+
+/// Serialize `as_num`, stored wrapped into `int`, as unsigned.
+fn serialize_as_num<S: Serializer>(as_num: &i32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u32(*as_num as u32)
+}
+
+/// Reports a paged query goes through.
+#[derive(Clone, Copy, Debug)]
+enum ReportFilter {
+    OverallType(OverallReportType),
+    /// Reports with an item of this case.
+    SpecificCase(ReportItemType),
+    PathVerification(i32),
+    /// Reports from or to this AS.
+    As(i32),
+}
+
+impl ReportFilter {
+    /// Query selecting the reports, for the planner to estimate.
+    /// Enum labels and IDs are inlined because `EXPLAIN` takes no parameters;
+    /// neither comes from the client as text.
+    fn select_sql(self) -> String {
+        match self {
+            Self::OverallType(overall_type) => format!(
+                "SELECT 1 FROM exchange_report WHERE overall_type = '{}'",
+                overall_type.as_str()
+            ),
+            Self::SpecificCase(specific_case) => format!(
+                "SELECT 1 FROM exchange_report e WHERE EXISTS (
+                    SELECT 1 FROM report_item WHERE parent_report = e.report_id AND specific_case = '{}'
+                )",
+                specific_case.as_str()
+            ),
+            Self::PathVerification(path_verification) => format!(
+                "SELECT 1 FROM exchange_report WHERE parent_verification = {path_verification}"
+            ),
+            Self::As(as_num) => format!(
+                "SELECT 1 FROM exchange_report WHERE from_as = {as_num} OR to_as = {as_num}"
+            ),
+        }
+    }
+}
+
+/// Reports a paged query lists with the routes they were observed on.
+#[derive(Clone, Copy, Debug)]
+enum RouteReportFilter {
+    OverallType(OverallReportType),
+    /// Reports with an item of this case.
+    SpecificCase(ReportItemType),
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RpslObj {
+    pub rpsl_obj_name: String,
+    pub body: String,
+    pub recorded_time: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AutNumPolicies {
+    #[serde(serialize_with = "serialize_as_num")]
+    pub as_num: i32,
+    pub as_name: String,
+    /// IR of the `mp-import` and `mp-export` policies.
+    pub imports: Value,
+    pub exports: Value,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ExchangeReport {
+    pub report_id: i32,
+    #[serde(serialize_with = "serialize_as_num")]
+    pub from_as: i32,
+    #[serde(serialize_with = "serialize_as_num")]
+    pub to_as: i32,
+    pub import: bool,
+    pub overall_type: OverallReportType,
+    pub parent_verification: i32,
+    pub verification_run: i32,
+    pub report_index: i32,
+    pub hop_index: Option<i32>,
+    pub recorded_time: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AsReportCount {
+    #[serde(serialize_with = "serialize_as_num")]
+    pub as_num: i32,
+    pub report_count: i64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AsReportItemCount {
+    #[serde(serialize_with = "serialize_as_num")]
+    pub as_num: i32,
+    pub report_item_count: i64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RouteReportCount {
+    pub observed_route_id: i32,
+    pub raw_line: String,
+    pub address_prefix: String,
+    pub recorded_time: String,
+    pub report_count: i64,
+}
+
+/// Report on an observed route, with one of its items if any.
+#[derive(Clone, Debug, Serialize)]
+pub struct RouteReportItem {
+    pub report_id: i32,
+    #[serde(serialize_with = "serialize_as_num")]
+    pub source_as: i32,
+    #[serde(serialize_with = "serialize_as_num")]
+    pub destination_as: i32,
+    pub import: bool,
+    pub overall_type: OverallReportType,
+    pub exchange_report_time: String,
+    pub observed_route_id: i32,
+    pub raw_line: String,
+    pub address_prefix: String,
+    pub observed_route_time: String,
+    pub report_category: Option<OverallReportType>,
+    pub report_specific_case: Option<ReportItemType>,
+    pub report_string_content: Option<String>,
+    pub report_numeric_content: Option<i32>,
+}
+
+/// Report item with the report it belongs to.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportItemDetail {
+    pub report_id: i32,
+    #[serde(serialize_with = "serialize_as_num")]
+    pub source_as: i32,
+    #[serde(serialize_with = "serialize_as_num")]
+    pub destination_as: i32,
+    pub import: bool,
+    pub overall_type: OverallReportType,
+    pub exchange_report_time: String,
+    pub report_item_id: i32,
+    pub report_category: OverallReportType,
+    pub report_specific_case: ReportItemType,
+    pub report_string_content: Option<String>,
+    pub report_numeric_content: Option<i32>,
+}
+
+/// Route observed with `as_num` on its path.
+#[derive(Clone, Debug, Serialize)]
+pub struct AsRoute {
+    #[serde(serialize_with = "serialize_as_num")]
+    pub as_num: i32,
+    pub observed_route_id: i32,
+    pub raw_line: String,
+    pub address_prefix: String,
+    pub recorded_time: String,
+}
+
+pub async fn rpsl_obj(pool: &Pool<Postgres>, rpsl_obj_name: &str) -> sqlx::Result<Option<RpslObj>> {
+    sqlx::query_as!(
+        RpslObj,
+        r#"SELECT rpsl_obj_name, body, recorded_time::text AS "recorded_time!"
+        FROM rpsl_obj WHERE rpsl_obj_name = $1"#,
+        rpsl_obj_name
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn aut_num(pool: &Pool<Postgres>, as_num: i32) -> sqlx::Result<Option<AutNumPolicies>> {
+    sqlx::query_as!(
+        AutNumPolicies,
+        "SELECT as_num, as_name, imports, exports FROM aut_num WHERE as_num = $1",
+        as_num
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Reports on the route observed as `observed_route_id`.
+pub async fn verification_reports(
+    pool: &Pool<Postgres>,
+    observed_route_id: i32,
+) -> sqlx::Result<Vec<ExchangeReport>> {
+    sqlx::query_as!(
+        ExchangeReport,
+        r#"SELECT
+            report_id, from_as, to_as, import,
+            overall_type AS "overall_type: OverallReportType",
+            parent_verification, verification_run, report_index, hop_index,
+            recorded_time::text AS "recorded_time!"
+        FROM exchange_report
+        WHERE parent_verification = (
+            SELECT path_verification FROM observed_route WHERE observed_route_id = $1
+        )"#,
+        observed_route_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Page of reports of `overall_type` after `after`, at most `limit`.
+pub async fn reports_of_overall_type(
+    pool: &Pool<Postgres>,
+    overall_type: OverallReportType,
+    after: Option<Cursor>,
+    limit: i64,
+) -> sqlx::Result<Page<ExchangeReport>> {
+    exchange_report_page(pool, ReportFilter::OverallType(overall_type), after, limit).await
+}
+
+/// Page of reports from or to `as_num` after `after`, at most `limit`.
+pub async fn reports_for_as(
+    pool: &Pool<Postgres>,
+    as_num: i32,
+    after: Option<Cursor>,
+    limit: i64,
+) -> sqlx::Result<Page<ExchangeReport>> {
+    exchange_report_page(pool, ReportFilter::As(as_num), after, limit).await
+}
+
+async fn exchange_report_page(
+    pool: &Pool<Postgres>,
+    filter: ReportFilter,
+    after: Option<Cursor>,
+    limit: i64,
+) -> sqlx::Result<Page<ExchangeReport>> {
+    let reports = report_page(pool, filter, after, limit).await?;
+    let report_ids: Vec<_> = reports.iter().map(|report| report.report_id).collect();
+    let items = sqlx::query_as!(
+        ExchangeReport,
+        r#"SELECT
+            report_id, from_as, to_as, import,
+            overall_type AS "overall_type: OverallReportType",
+            parent_verification, verification_run, report_index, hop_index,
+            recorded_time::text AS "recorded_time!"
+        FROM exchange_report
+        WHERE report_id = ANY($1)
+        ORDER BY recorded_time, report_id"#,
+        &report_ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    let total_reports = count_reports(pool, filter).await?;
+    Ok(Page::new(items, &reports, limit, total_reports))
+}
+
+/// ASes in at least one report of `overall_type`,
+/// with the number of such reports.
+pub async fn as_for_overall_report_type(
+    pool: &Pool<Postgres>,
+    overall_type: OverallReportType,
+) -> sqlx::Result<Vec<AsReportCount>> {
+    sqlx::query_as!(
+        AsReportCount,
+        r#"WITH filtered_exchange_report AS (
+            SELECT report_id, from_as, to_as FROM exchange_report WHERE overall_type = $1
+        )
+        SELECT as_num AS "as_num!", count(*) AS "report_count!" FROM (
+            SELECT report_id, from_as AS as_num FROM filtered_exchange_report
+            UNION
+            SELECT report_id, to_as AS as_num FROM filtered_exchange_report
+        ) AS aggregated_as
+        GROUP BY as_num
+        ORDER BY as_num"#,
+        overall_type as _
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Routes in at least one import report of `overall_type` by a provider
+/// in the latest AS relationship snapshot, with the number of such reports.
+/// Potentially route leaks if bad.
+pub async fn route_for_overall_report_type_import_by_provider(
+    pool: &Pool<Postgres>,
+    overall_type: OverallReportType,
+) -> sqlx::Result<Vec<RouteReportCount>> {
+    sqlx::query_as!(
+        RouteReportCount,
+        r#"SELECT
+            observed_route_id,
+            raw_line,
+            text(address_prefix) AS "address_prefix!",
+            observed_route.recorded_time::text AS "recorded_time!",
+            count(*) AS "report_count!"
+        FROM exchange_report
+        JOIN provide_customer ON from_as = customer AND to_as = provider
+        JOIN observed_route ON parent_verification = path_verification
+        WHERE overall_type = $1 AND import
+        AND as_rel_source = (
+            SELECT as_rel_source_id FROM as_rel_source
+            ORDER BY snapshot_date DESC, as_rel_source_id DESC
+            LIMIT 1
+        )
+        GROUP BY observed_route_id, raw_line, address_prefix, observed_route.recorded_time"#,
+        overall_type as _
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Top ten providers in import reports of `overall_type`
+/// in the latest AS relationship snapshot, by number of such reports.
+/// Potential route leakers if bad.
+pub async fn as_for_overall_report_type_import_by_provider(
+    pool: &Pool<Postgres>,
+    overall_type: OverallReportType,
+) -> sqlx::Result<Vec<AsReportCount>> {
+    sqlx::query_as!(
+        AsReportCount,
+        r#"SELECT to_as AS as_num, count(*) AS "report_count!"
+        FROM exchange_report
+        JOIN provide_customer ON from_as = customer AND to_as = provider
+        WHERE overall_type = $1 AND import
+        AND as_rel_source = (
+            SELECT as_rel_source_id FROM as_rel_source
+            ORDER BY snapshot_date DESC, as_rel_source_id DESC
+            LIMIT 1
+        )
+        GROUP BY to_as
+        ORDER BY count(*) DESC
+        LIMIT 10"#,
+        overall_type as _
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Top ten providers importing without import or export rules to check,
+/// by number of such report items.
+/// They should specify RPSL rules.
+pub async fn as_lacking_rules_for_import_by_provider(
+    pool: &Pool<Postgres>,
+) -> sqlx::Result<Vec<AsReportItemCount>> {
+    sqlx::query_as!(
+        AsReportItemCount,
+        r#"SELECT to_as AS as_num, count(*) AS "report_item_count!"
+        FROM report_item
+        JOIN exchange_report ON parent_report = report_id
+        JOIN provide_customer ON from_as = customer AND to_as = provider
+        WHERE import
+        AND specific_case IN ('unrec_import_empty', 'unrec_export_empty')
+        AND as_rel_source = (
+            SELECT as_rel_source_id FROM as_rel_source
+            ORDER BY snapshot_date DESC, as_rel_source_id DESC
+            LIMIT 1
+        )
+        GROUP BY to_as
+        ORDER BY count(*) DESC
+        LIMIT 10"#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// ASes in at least one report item of `specific_case`,
+/// with the number of such report items.
+pub async fn as_for_report_item_type(
+    pool: &Pool<Postgres>,
+    specific_case: ReportItemType,
+) -> sqlx::Result<Vec<AsReportItemCount>> {
+    sqlx::query_as!(
+        AsReportItemCount,
+        r#"WITH filtered_report_item AS (
+            SELECT report_item_id, from_as, to_as
+            FROM report_item JOIN exchange_report ON parent_report = report_id
+            WHERE specific_case = $1
+        )
+        SELECT as_num AS "as_num!", count(*) AS "report_item_count!" FROM (
+            SELECT report_item_id, from_as AS as_num FROM filtered_report_item
+            UNION
+            SELECT report_item_id, to_as AS as_num FROM filtered_report_item
+        ) AS aggregated_as
+        GROUP BY as_num
+        ORDER BY as_num"#,
+        specific_case as _
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Page of reports of `overall_type` and routes they were observed on
+/// after `after`, at most `limit` pairs, with their items.
+pub async fn for_overall_report_type(
+    pool: &Pool<Postgres>,
+    overall_type: OverallReportType,
+    after: Option<Cursor>,
+    limit: i64,
+) -> sqlx::Result<Page<RouteReportItem>> {
+    let filter = RouteReportFilter::OverallType(overall_type);
+    let keys = report_route_page(pool, filter, after, limit).await?;
+    let (report_ids, observed_route_ids) = report_route_ids(&keys);
+    let items = sqlx::query_as!(
+        RouteReportItem,
+        r#"SELECT
+            e.report_id,
+            e.from_as AS source_as,
+            e.to_as AS destination_as,
+            e.import,
+            e.overall_type AS "overall_type: OverallReportType",
+            e.recorded_time::text AS "exchange_report_time!",
+            r.observed_route_id,
+            r.raw_line,
+            text(r.address_prefix) AS "address_prefix!",
+            r.recorded_time::text AS "observed_route_time!",
+            ri.category AS "report_category?: OverallReportType",
+            ri.specific_case AS "report_specific_case?: ReportItemType",
+            ri.str_content AS "report_string_content?",
+            ri.num_content AS "report_numeric_content?"
+        FROM exchange_report e
+        JOIN observed_route r ON e.parent_verification = r.path_verification
+        LEFT JOIN report_item ri ON e.report_id = ri.parent_report
+        WHERE (e.report_id, r.observed_route_id) IN (SELECT * FROM unnest($1::int[], $2::int[]))
+        ORDER BY e.recorded_time, e.report_id, r.observed_route_id, ri.report_item_id"#,
+        &report_ids,
+        &observed_route_ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    let total_reports = count_reports(pool, ReportFilter::OverallType(overall_type)).await?;
+    Ok(Page::new(items, &keys, limit, total_reports))
+}
+
+/// Page of reports with an item of `specific_case`
+/// and routes they were observed on after `after`, at most `limit` pairs,
+/// with their items of that case.
+pub async fn for_report_item_type(
+    pool: &Pool<Postgres>,
+    specific_case: ReportItemType,
+    after: Option<Cursor>,
+    limit: i64,
+) -> sqlx::Result<Page<RouteReportItem>> {
+    let filter = RouteReportFilter::SpecificCase(specific_case);
+    let keys = report_route_page(pool, filter, after, limit).await?;
+    let (report_ids, observed_route_ids) = report_route_ids(&keys);
+    let items = sqlx::query_as!(
+        RouteReportItem,
+        r#"SELECT
+            e.report_id,
+            e.from_as AS source_as,
+            e.to_as AS destination_as,
+            e.import,
+            e.overall_type AS "overall_type: OverallReportType",
+            e.recorded_time::text AS "exchange_report_time!",
+            r.observed_route_id,
+            r.raw_line,
+            text(r.address_prefix) AS "address_prefix!",
+            r.recorded_time::text AS "observed_route_time!",
+            ri.category AS "report_category?: OverallReportType",
+            ri.specific_case AS "report_specific_case?: ReportItemType",
+            ri.str_content AS "report_string_content?",
+            ri.num_content AS "report_numeric_content?"
+        FROM exchange_report e
+        JOIN observed_route r ON e.parent_verification = r.path_verification
+        JOIN report_item ri ON e.report_id = ri.parent_report
+        WHERE (e.report_id, r.observed_route_id) IN (SELECT * FROM unnest($1::int[], $2::int[]))
+        AND ri.specific_case = $3
+        ORDER BY e.recorded_time, e.report_id, r.observed_route_id, ri.report_item_id"#,
+        &report_ids,
+        &observed_route_ids,
+        specific_case as _,
+    )
+    .fetch_all(pool)
+    .await?;
+    let total_reports = count_reports(pool, ReportFilter::SpecificCase(specific_case)).await?;
+    Ok(Page::new(items, &keys, limit, total_reports))
+}
+
+/// Page of reports on a route announcing `address_prefix` after `after`,
+/// at most `limit`, with their items.
+pub async fn for_address_prefix(
+    pool: &Pool<Postgres>,
+    address_prefix: IpNetwork,
+    after: Option<Cursor>,
+    limit: i64,
+) -> sqlx::Result<Page<ReportItemDetail>> {
+    let path_verification = sqlx::query_scalar!(
+        r#"SELECT path_verification FROM observed_route
+        WHERE address_prefix = $1 AND NOT withdrawal
+        LIMIT 1"#,
+        address_prefix
+    )
+    .fetch_optional(pool)
+    .await?
+    .flatten();
+    let Some(path_verification) = path_verification else {
+        return Ok(Page::empty());
+    };
+
+    let filter = ReportFilter::PathVerification(path_verification);
+    let reports = report_page(pool, filter, after, limit).await?;
+    let report_ids: Vec<_> = reports.iter().map(|report| report.report_id).collect();
+    let items = sqlx::query_as!(
+        ReportItemDetail,
+        r#"SELECT
+            e.report_id,
+            e.from_as AS source_as,
+            e.to_as AS destination_as,
+            e.import,
+            e.overall_type AS "overall_type: OverallReportType",
+            e.recorded_time::text AS "exchange_report_time!",
+            ri.report_item_id,
+            ri.category AS "report_category: OverallReportType",
+            ri.specific_case AS "report_specific_case: ReportItemType",
+            ri.str_content AS report_string_content,
+            ri.num_content AS report_numeric_content
+        FROM exchange_report e
+        JOIN report_item ri ON e.report_id = ri.parent_report
+        WHERE e.report_id = ANY($1)
+        ORDER BY e.recorded_time, e.report_id, ri.report_item_id"#,
+        &report_ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    let total_reports = count_reports(pool, filter).await?;
+    Ok(Page::new(items, &reports, limit, total_reports))
+}
+
+/// Up to `limit` reports selected by `filter` after `after`,
+/// in `(recorded_time, report_id)` order.
+async fn report_page(
+    pool: &Pool<Postgres>,
+    filter: ReportFilter,
+    after: Option<Cursor>,
+    limit: i64,
+) -> sqlx::Result<Vec<Cursor>> {
+    let (after_micros, after_report) = (
+        after.map(|cursor| cursor.recorded_micros),
+        after.map(|cursor| cursor.report_id),
+    );
+    // `recorded_time` is a `timestamp`, so epoch arithmetic on it is exact.
+    let reports = match filter {
+        ReportFilter::OverallType(overall_type) => {
+            sqlx::query_as!(
+                Cursor,
+                r#"SELECT
+                    (extract(epoch FROM recorded_time) * 1000000)::bigint AS "recorded_micros!",
+                    report_id,
+                    NULL::int AS observed_route_id
+                FROM exchange_report
+                WHERE overall_type = $1
+                AND ($2::bigint IS NULL OR (recorded_time, report_id) > ('epoch'::timestamp + $2 * interval '1 microsecond', $3))
+                ORDER BY recorded_time, report_id
+                LIMIT $4"#,
+                overall_type as _,
+                after_micros,
+                after_report,
+                limit,
+            )
+            .fetch_all(pool)
+            .await?
+        }
+        ReportFilter::SpecificCase(specific_case) => {
+            sqlx::query_as!(
+                Cursor,
+                r#"SELECT
+                    (extract(epoch FROM recorded_time) * 1000000)::bigint AS "recorded_micros!",
+                    report_id,
+                    NULL::int AS observed_route_id
+                FROM exchange_report e
+                WHERE EXISTS (
+                    SELECT 1 FROM report_item
+                    WHERE parent_report = e.report_id AND specific_case = $1
+                )
+                AND ($2::bigint IS NULL OR (recorded_time, report_id) > ('epoch'::timestamp + $2 * interval '1 microsecond', $3))
+                ORDER BY recorded_time, report_id
+                LIMIT $4"#,
+                specific_case as _,
+                after_micros,
+                after_report,
+                limit,
+            )
+            .fetch_all(pool)
+            .await?
+        }
+        ReportFilter::PathVerification(path_verification) => {
+            sqlx::query_as!(
+                Cursor,
+                r#"SELECT
+                    (extract(epoch FROM recorded_time) * 1000000)::bigint AS "recorded_micros!",
+                    report_id,
+                    NULL::int AS observed_route_id
+                FROM exchange_report
+                WHERE parent_verification = $1
+                AND ($2::bigint IS NULL OR (recorded_time, report_id) > ('epoch'::timestamp + $2 * interval '1 microsecond', $3))
+                ORDER BY recorded_time, report_id
+                LIMIT $4"#,
+                path_verification,
+                after_micros,
+                after_report,
+                limit,
+            )
+            .fetch_all(pool)
+            .await?
+        }
+        ReportFilter::As(as_num) => {
+            // Merge the pages of reports from and to the AS
+            // so each side walks its own index.
+            sqlx::query_as!(
+                Cursor,
+                r#"SELECT
+                    (extract(epoch FROM recorded_time) * 1000000)::bigint AS "recorded_micros!",
+                    report_id,
+                    NULL::int AS observed_route_id
+                FROM exchange_report
+                WHERE report_id IN (
+                    (
+                        SELECT report_id FROM exchange_report
+                        WHERE from_as = $1
+                        AND ($2::bigint IS NULL OR (recorded_time, report_id) > ('epoch'::timestamp + $2 * interval '1 microsecond', $3))
+                        ORDER BY recorded_time, report_id
+                        LIMIT $4
+                    )
+                    UNION
+                    (
+                        SELECT report_id FROM exchange_report
+                        WHERE to_as = $1
+                        AND ($2::bigint IS NULL OR (recorded_time, report_id) > ('epoch'::timestamp + $2 * interval '1 microsecond', $3))
+                        ORDER BY recorded_time, report_id
+                        LIMIT $4
+                    )
+                )
+                ORDER BY recorded_time, report_id
+                LIMIT $4"#,
+                as_num,
+                after_micros,
+                after_report,
+                limit,
+            )
+            .fetch_all(pool)
+            .await?
+        }
+    };
+    Ok(reports)
+}
+
+/// Up to `limit` pairs of a report `filter` selects
+/// and a route it was observed on, after `after`,
+/// in `(recorded_time, report_id, observed_route_id)` order.
+/// A cursor without a route continues after the report.
+async fn report_route_page(
+    pool: &Pool<Postgres>,
+    filter: RouteReportFilter,
+    after: Option<Cursor>,
+    limit: i64,
+) -> sqlx::Result<Vec<Cursor>> {
+    let (after_micros, after_report, after_route) = (
+        after.map(|cursor| cursor.recorded_micros),
+        after.map(|cursor| cursor.report_id),
+        after.and_then(|cursor| cursor.observed_route_id),
+    );
+    let keys = match filter {
+        RouteReportFilter::OverallType(overall_type) => {
+            sqlx::query_as!(
+                Cursor,
+                r#"SELECT
+                    (extract(epoch FROM e.recorded_time) * 1000000)::bigint AS "recorded_micros!",
+                    e.report_id,
+                    r.observed_route_id AS "observed_route_id?"
+                FROM exchange_report e
+                JOIN observed_route r ON e.parent_verification = r.path_verification
+                WHERE e.overall_type = $1
+                AND ($2::bigint IS NULL OR (e.recorded_time, e.report_id, r.observed_route_id) > (
+                    'epoch'::timestamp + $2 * interval '1 microsecond', $3, COALESCE($4, 2147483647)
+                ))
+                ORDER BY e.recorded_time, e.report_id, r.observed_route_id
+                LIMIT $5"#,
+                overall_type as _,
+                after_micros,
+                after_report,
+                after_route,
+                limit,
+            )
+            .fetch_all(pool)
+            .await?
+        }
+        RouteReportFilter::SpecificCase(specific_case) => {
+            sqlx::query_as!(
+                Cursor,
+                r#"SELECT
+                    (extract(epoch FROM e.recorded_time) * 1000000)::bigint AS "recorded_micros!",
+                    e.report_id,
+                    r.observed_route_id AS "observed_route_id?"
+                FROM exchange_report e
+                JOIN observed_route r ON e.parent_verification = r.path_verification
+                WHERE EXISTS (
+                    SELECT 1 FROM report_item
+                    WHERE parent_report = e.report_id AND specific_case = $1
+                )
+                AND ($2::bigint IS NULL OR (e.recorded_time, e.report_id, r.observed_route_id) > (
+                    'epoch'::timestamp + $2 * interval '1 microsecond', $3, COALESCE($4, 2147483647)
+                ))
+                ORDER BY e.recorded_time, e.report_id, r.observed_route_id
+                LIMIT $5"#,
+                specific_case as _,
+                after_micros,
+                after_report,
+                after_route,
+                limit,
+            )
+            .fetch_all(pool)
+            .await?
+        }
+    };
+    Ok(keys)
+}
+
+/// Report and observed route IDs of `keys` from [`report_route_page`].
+fn report_route_ids(keys: &[Cursor]) -> (Vec<i32>, Vec<i32>) {
+    keys.iter()
+        .filter_map(|key| Some((key.report_id, key.observed_route_id?)))
+        .unzip()
+}
+
+/// Count the reports `filter` selects,
+/// exactly up to [`EXACT_COUNT_LIMIT`] and by the planner's estimate beyond.
+async fn count_reports(pool: &Pool<Postgres>, filter: ReportFilter) -> sqlx::Result<Count> {
+    let counted = match filter {
+        ReportFilter::OverallType(overall_type) => {
+            sqlx::query_scalar!(
+                r#"SELECT count(*) AS "count!" FROM (
+                    SELECT 1 FROM exchange_report WHERE overall_type = $1 LIMIT $2
+                ) capped"#,
+                overall_type as _,
+                EXACT_COUNT_LIMIT,
+            )
+            .fetch_one(pool)
+            .await?
+        }
+        ReportFilter::SpecificCase(specific_case) => {
+            sqlx::query_scalar!(
+                r#"SELECT count(*) AS "count!" FROM (
+                    SELECT DISTINCT parent_report FROM report_item WHERE specific_case = $1 LIMIT $2
+                ) capped"#,
+                specific_case as _,
+                EXACT_COUNT_LIMIT,
+            )
+            .fetch_one(pool)
+            .await?
+        }
+        ReportFilter::PathVerification(path_verification) => {
+            sqlx::query_scalar!(
+                r#"SELECT count(*) AS "count!" FROM (
+                    SELECT 1 FROM exchange_report WHERE parent_verification = $1 LIMIT $2
+                ) capped"#,
+                path_verification,
+                EXACT_COUNT_LIMIT,
+            )
+            .fetch_one(pool)
+            .await?
+        }
+        ReportFilter::As(as_num) => {
+            sqlx::query_scalar!(
+                r#"SELECT count(*) AS "count!" FROM (
+                    SELECT 1 FROM exchange_report WHERE from_as = $1 OR to_as = $1 LIMIT $2
+                ) capped"#,
+                as_num,
+                EXACT_COUNT_LIMIT,
+            )
+            .fetch_one(pool)
+            .await?
+        }
+    };
+    if counted < EXACT_COUNT_LIMIT {
+        return Ok(Count {
+            count: counted,
+            exact: true,
+        });
+    }
+
+    let plan: Value = sqlx::query_scalar(&format!("EXPLAIN (FORMAT JSON) {}", filter.select_sql()))
+        .fetch_one(pool)
+        .await?;
+    let estimate = plan[0]["Plan"]["Plan Rows"].as_f64().unwrap_or_default() as i64;
+    Ok(Count {
+        count: estimate.max(counted),
+        exact: false,
+    })
+}
+
+/// Routes observed with `as_num` in a report.
+pub async fn routes_for_as(pool: &Pool<Postgres>, as_num: i32) -> sqlx::Result<Vec<AsRoute>> {
+    sqlx::query_as!(
+        AsRoute,
+        r#"SELECT
+            e.from_as AS "as_num!",
+            o.observed_route_id AS "observed_route_id!",
+            o.raw_line AS "raw_line!",
+            text(o.address_prefix) AS "address_prefix!",
+            o.recorded_time::text AS "recorded_time!"
+        FROM exchange_report e
+        JOIN observed_route o ON e.parent_verification = o.path_verification
+        WHERE e.from_as = $1
+        UNION
+        SELECT
+            e.to_as,
+            o.observed_route_id,
+            o.raw_line,
+            text(o.address_prefix),
+            o.recorded_time::text
+        FROM exchange_report e
+        JOIN observed_route o ON e.parent_verification = o.path_verification
+        WHERE e.to_as = $1"#,
+        as_num
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Executor, PgPool};
+
+    use super::*;
+
+    #[test]
+    fn cursors() {
+        let cursor = Cursor {
+            recorded_micros: 1_687_212_000_123_456,
+            report_id: 42,
+            observed_route_id: None,
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+        let route_cursor = Cursor {
+            observed_route_id: Some(7),
+            ..cursor
+        };
+        assert_eq!(Cursor::decode(&route_cursor.encode()), Some(route_cursor));
+        for invalid in ["", "zz", &hex::encode("42"), &hex::encode("1.2.3.4")] {
+            assert_eq!(Cursor::decode(invalid), None);
+        }
+
+        let reports = [cursor; 2];
+        let full = Page::new(
+            Vec::<()>::new(),
+            &reports,
+            2,
+            Page::<()>::empty().total_reports,
+        );
+        assert_eq!(full.next_cursor, Some(cursor.encode()));
+        let last = Page::new(Vec::<()>::new(), &reports, 3, full.total_reports);
+        assert_eq!(last.next_cursor, None);
+    }
+
+    async fn fixture_pool(pool: &PgPool) -> sqlx::Result<()> {
+        pool.execute(include_str!("../../demo_v1.sql")).await?;
+        pool.execute(include_str!("../../trigger_only.sql")).await?;
+        pool.execute(include_str!("../fixtures/reports.sql"))
+            .await?;
+        Ok(())
+    }
+
+    /// Report IDs of every page of `filter` at `limit` reports per page.
+    async fn walk_reports(
+        pool: &PgPool,
+        filter: ReportFilter,
+        limit: i64,
+    ) -> sqlx::Result<Vec<i32>> {
+        let (mut report_ids, mut after) = (Vec::new(), None);
+        loop {
+            let page = report_page(pool, filter, after, limit).await?;
+            report_ids.extend(page.iter().map(|report| report.report_id));
+            if (page.len() as i64) < limit {
+                return Ok(report_ids);
+            }
+            after = page.last().copied();
+        }
+    }
+
+    /// Report and route IDs of every page of `filter` at `limit` pairs per page.
+    async fn walk_report_routes(
+        pool: &PgPool,
+        filter: RouteReportFilter,
+        limit: i64,
+    ) -> sqlx::Result<Vec<(i32, i32)>> {
+        let (mut pairs, mut after) = (Vec::new(), None);
+        loop {
+            let page = report_route_page(pool, filter, after, limit).await?;
+            let (report_ids, observed_route_ids) = report_route_ids(&page);
+            pairs.extend(report_ids.into_iter().zip(observed_route_ids));
+            if (page.len() as i64) < limit {
+                return Ok(pairs);
+            }
+            after = page.last().copied();
+        }
+    }
+
+    /// Pairs of reports of `overall_type` or with an item of `specific_case`
+    /// and their routes, in one unpaged query.
+    async fn unpaged_report_routes(
+        pool: &PgPool,
+        overall_type: Option<OverallReportType>,
+        specific_case: Option<ReportItemType>,
+    ) -> sqlx::Result<Vec<(i32, i32)>> {
+        let rows = sqlx::query!(
+            r#"SELECT e.report_id, r.observed_route_id
+            FROM exchange_report e
+            JOIN observed_route r ON e.parent_verification = r.path_verification
+            WHERE ($1::overall_report_type IS NULL OR e.overall_type = $1)
+            AND ($2::report_item_type IS NULL OR EXISTS (
+                SELECT 1 FROM report_item WHERE parent_report = e.report_id AND specific_case = $2
+            ))
+            ORDER BY e.recorded_time, e.report_id, r.observed_route_id"#,
+            overall_type as _,
+            specific_case as _,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.report_id, row.observed_route_id))
+            .collect())
+    }
+
+    #[sqlx::test]
+    async fn pages_walk_reports_in_order(pool: PgPool) -> sqlx::Result<()> {
+        fixture_pool(&pool).await?;
+        let unpaged_reports = |as_num: Option<i32>, overall_type: Option<OverallReportType>| {
+            sqlx::query_scalar!(
+                r#"SELECT report_id FROM exchange_report
+                WHERE ($1::int IS NULL OR from_as = $1 OR to_as = $1)
+                AND ($2::overall_report_type IS NULL OR overall_type = $2)
+                ORDER BY recorded_time, report_id"#,
+                as_num,
+                overall_type as _,
+            )
+            .fetch_all(&pool)
+        };
+        // AS3356 is on every report, from it on some and to it on others,
+        // so the pages of both sides of the `UNION` interleave.
+        let cases = [
+            (
+                ReportFilter::OverallType(OverallReportType::Bad),
+                unpaged_reports(None, Some(OverallReportType::Bad)).await?,
+            ),
+            (
+                ReportFilter::As(3356),
+                unpaged_reports(Some(3356), None).await?,
+            ),
+            (
+                ReportFilter::As(4_200_000_000_u32 as i32),
+                unpaged_reports(Some(4_200_000_000_u32 as i32), None).await?,
+            ),
+        ];
+        for (filter, unpaged) in cases {
+            assert!(unpaged.len() > 1);
+            for limit in [1, 2] {
+                let paged = walk_reports(&pool, filter, limit).await?;
+                assert_eq!(paged, unpaged, "{filter:?} at limit {limit}");
+            }
+        }
+
+        let bad = OverallReportType::Bad;
+        let err_filter = ReportItemType::ErrFilter;
+        let cases = [
+            (
+                RouteReportFilter::OverallType(bad),
+                unpaged_report_routes(&pool, Some(bad), None).await?,
+            ),
+            (
+                RouteReportFilter::SpecificCase(err_filter),
+                unpaged_report_routes(&pool, None, Some(err_filter)).await?,
+            ),
+        ];
+        for (filter, unpaged) in cases {
+            for limit in [1, 2] {
+                let paged = walk_report_routes(&pool, filter, limit).await?;
+                assert_eq!(paged, unpaged, "{filter:?} at limit {limit}");
+            }
+        }
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn report_cursors_continue_past_every_route(pool: PgPool) -> sqlx::Result<()> {
+        fixture_pool(&pool).await?;
+        let bad = OverallReportType::Bad;
+        let filter = RouteReportFilter::OverallType(bad);
+        let unpaged = unpaged_report_routes(&pool, Some(bad), None).await?;
+        // The second pair is the first of a report observed on three routes.
+        let second = report_route_page(&pool, filter, None, 2).await?[1];
+        assert_eq!(
+            unpaged[1..4]
+                .iter()
+                .filter(|(report_id, _)| *report_id == second.report_id)
+                .count(),
+            3
+        );
+
+        // A cursor without a route, e.g., from a page of whole reports,
+        // skips the rest of its report's routes.
+        let after = Cursor {
+            observed_route_id: None,
+            ..second
+        };
+        let rest = report_route_page(&pool, filter, Some(after), 100).await?;
+        let (report_ids, observed_route_ids) = report_route_ids(&rest);
+        let rest: Vec<_> = report_ids.into_iter().zip(observed_route_ids).collect();
+        assert_eq!(rest, unpaged[4..]);
+        Ok(())
+    }
+}
@@ -0,0 +1,845 @@
+//! Verify observed routes and store the reports.
+//! Each unique route path is verified once per IR;
+//! further observations of it reuse the stored verification.
+//!
+//! Each input entry is stored in its own transaction with its position in
+//! the input, so an interrupted run can be resumed without duplicates.
+//! Checkpoints of seekable inputs also keep the byte offset of the current
+//! record, so resuming seeks there instead of decoding the input again.
+use anyhow::{Context, Result};
+use log::{debug, error, info};
+use sqlx::{postgres::PgConnection, types::ipnetwork::IpNetwork, Pool, Postgres};
+use tokio::{
+    sync::mpsc,
+    task::{spawn_blocking, JoinHandle},
+};
+
+use crate::{adapter::*, report::*, report_type::OverallReportType, run::*, stored_ir::IrSource};
+
+/// Number of decoded lines allowed to wait for the database writer.
+/// Decoding pauses when this many are pending, bounding memory use.
+const LINE_BUFFER: usize = 1024;
+
+/// Number of input entries between saved checkpoints.
+const CHECKPOINT_INTERVAL: i64 = 1024;
+
+/// Number of stored routes [`reverify`] reads at a time.
+const REVERIFY_BATCH: i64 = 1024;
+
+/// How a recording command runs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RecordOptions {
+    /// Continue the latest unfinished run on the same inputs
+    /// instead of starting a new one.
+    pub resume: bool,
+    /// Stop once this many observed routes are stored, e.g., to keep demo
+    /// databases small, leaving the run unfinished to resume later.
+    /// `None` to read the whole input.
+    pub max_routes: Option<i32>,
+}
+
+/// Verify the routes in the MRT file `mrt_file` and store the reports.
+pub async fn record_reports(
+    pool: &Pool<Postgres>,
+    source: IrSource<'_>,
+    mrt_file: &str,
+    options: RecordOptions,
+) -> Result<()> {
+    let mut active = start_run(pool, source, mrt_file, options).await?;
+    debug!("Streaming the MRT file.");
+    let mrt_file = mrt_file.to_owned();
+    record_lines(pool, &mut active, move |offset| {
+        stream_mrt(mrt_file, offset)
+    })
+    .await
+}
+
+/// Like [`record_reports`], but with routes from the text export
+/// `text_file`; see [`crate::text`] for the formats.
+pub async fn record_text_routes(
+    pool: &Pool<Postgres>,
+    source: IrSource<'_>,
+    text_file: &str,
+    options: RecordOptions,
+) -> Result<()> {
+    let mut active = start_run(pool, source, text_file, options).await?;
+    debug!("Streaming the text export.");
+    let text_file = text_file.to_owned();
+    record_lines(pool, &mut active, move |offset| {
+        stream_text_routes(text_file, offset)
+    })
+    .await
+}
+
+/// Verify and store the [`Line`]s from the iterator `open` returns.
+async fn record_lines<I>(
+    pool: &Pool<Postgres>,
+    active: &mut ActiveRun,
+    open: impl FnOnce(Option<u64>) -> Result<I> + Send + 'static,
+) -> Result<()>
+where
+    I: InputEntries<Item = Result<Line>>,
+{
+    let (mut receiver, reader) = spawn_reader(open, active.input_index, active.offset);
+
+    let mut capped = false;
+    while let Some((offset, line)) = receiver.recv().await {
+        let input_index = active.next_input(pool, offset).await?;
+        let mut line = match line {
+            Ok(line) => line,
+            Err(why) => {
+                active.counts.n_failed += 1;
+                error!("Failed to parse line: {:?}", why);
+                continue;
+            }
+        };
+        let observed = observed_route(&line);
+        match record_line(pool, &observed, &mut line, active, input_index).await {
+            Ok(_) => {
+                if !active.count_route() {
+                    capped = true;
+                    break;
+                }
+            }
+            Err(why) => {
+                active.counts.n_failed += 1;
+                error!("Failed to record observed route {:?}: {:?}", observed, why);
+            }
+        }
+    }
+
+    drop(receiver);
+    reader.await??;
+    active.end(pool, capped).await
+}
+
+/// Verify the announcements in the BGP4MP dump `updates_file` and store
+/// them with the withdrawals, so the time each route appeared and
+/// disappeared is kept.
+pub async fn record_updates(
+    pool: &Pool<Postgres>,
+    source: IrSource<'_>,
+    updates_file: &str,
+    options: RecordOptions,
+) -> Result<()> {
+    let mut active = start_run(pool, source, updates_file, options).await?;
+
+    debug!("Streaming the update dump.");
+    let updates_file = updates_file.to_owned();
+    let (mut receiver, reader) = spawn_reader(
+        move |offset| stream_updates(updates_file, offset),
+        active.input_index,
+        active.offset,
+    );
+
+    let mut capped = false;
+    while let Some((offset, update)) = receiver.recv().await {
+        let input_index = active.next_input(pool, offset).await?;
+        let mut update = match update {
+            Ok(update) => update,
+            Err(why) => {
+                active.counts.n_failed += 1;
+                error!("Failed to parse update line: {:?}", why);
+                continue;
+            }
+        };
+        match record_update(pool, &mut update, &mut active, input_index).await {
+            Ok(_) => {
+                if !active.count_route() {
+                    capped = true;
+                    break;
+                }
+            }
+            Err(why) => {
+                active.counts.n_failed += 1;
+                error!("Failed to record update {:?}: {:?}", update.update, why);
+            }
+        }
+    }
+
+    drop(receiver);
+    reader.await??;
+    active.end(pool, capped).await
+}
+
+/// Re-verify the routes observed in run `source_run_id`,
+/// by default the latest finished run,
+/// against the IR and AS relationships from `source`.
+/// The results form a new run with its own copies of the observed routes,
+/// so the source run and its reports stay for comparison.
+pub async fn reverify(
+    pool: &Pool<Postgres>,
+    source: IrSource<'_>,
+    source_run_id: Option<i32>,
+    options: RecordOptions,
+) -> Result<()> {
+    let source_run_id = match source_run_id {
+        Some(source_run_id) => source_run_id,
+        None => latest_finished_run(pool)
+            .await?
+            .context("No finished verification run to re-verify")?,
+    };
+    let input_file = format!("verification_run {source_run_id}");
+    let mut active = start_run(pool, source, &input_file, options).await?;
+
+    debug!("Re-verifying routes observed in run {source_run_id}.");
+    let mut capped = false;
+    'batches: loop {
+        let routes =
+            fetch_stored_routes(pool, source_run_id, active.input_index, REVERIFY_BATCH).await?;
+        if routes.is_empty() {
+            break;
+        }
+        for stored in routes {
+            active.input_index = stored.input_index;
+            let input_index = active.next_input(pool, None).await?;
+            match record_stored(pool, &stored, &mut active, input_index).await {
+                Ok(_) => {
+                    if !active.count_route() {
+                        capped = true;
+                        break 'batches;
+                    }
+                }
+                Err(why) => {
+                    active.counts.n_failed += 1;
+                    error!("Failed to re-verify {:?}: {:?}", stored, why);
+                }
+            }
+        }
+    }
+
+    active.end(pool, capped).await
+}
+
+/// Observed route read back from the database.
+#[derive(Clone, Debug)]
+struct StoredRoute {
+    observed_route_id: i32,
+    input_index: i64,
+    raw_line: String,
+    address_prefix: IpNetwork,
+    /// `None` for withdrawals.
+    as_path: Option<String>,
+}
+
+/// Verification run being recorded.
+struct ActiveRun {
+    query: QueryIr,
+    run: VerificationRun,
+    run_id: i32,
+    counts: RunCounts,
+    /// Position in the input of the next entry.
+    input_index: i64,
+    /// Record of the last entry, if the input can seek.
+    offset: Option<InputOffset>,
+    /// Routes left to store before stopping, `None` if unlimited.
+    routes_left: Option<i32>,
+}
+
+impl ActiveRun {
+    /// Position of the entry just received from the record at byte `offset`,
+    /// saving a checkpoint every [`CHECKPOINT_INTERVAL`] entries.
+    async fn next_input(
+        &mut self,
+        pool: &Pool<Postgres>,
+        offset: Option<u64>,
+    ) -> sqlx::Result<i64> {
+        let input_index = self.input_index;
+        self.input_index += 1;
+        if input_index > 0 && input_index % CHECKPOINT_INTERVAL == 0 {
+            // Entries before this one are processed, so resume from the
+            // record of the previous entry.
+            save_checkpoint(pool, self.run_id, input_index, self.offset, &self.counts).await?;
+        }
+        self.offset = match (self.offset, offset) {
+            (Some(current), Some(offset)) if current.offset == offset => Some(current),
+            (_, offset) => offset.map(|offset| InputOffset {
+                offset,
+                input_index,
+            }),
+        };
+        Ok(input_index)
+    }
+
+    /// Count a stored route.
+    /// `false` once [`RecordOptions::max_routes`] are stored.
+    fn count_route(&mut self) -> bool {
+        self.counts.n_observed_route += 1;
+        match &mut self.routes_left {
+            Some(routes_left) => {
+                *routes_left -= 1;
+                *routes_left > 0
+            }
+            None => true,
+        }
+    }
+
+    /// Finish the run, or if `capped`, save a checkpoint and leave it
+    /// unfinished so it can be resumed.
+    async fn end(&self, pool: &Pool<Postgres>, capped: bool) -> Result<()> {
+        save_checkpoint(
+            pool,
+            self.run_id,
+            self.input_index,
+            self.offset,
+            &self.counts,
+        )
+        .await?;
+        if capped {
+            info!(
+                "Stopped verification run {} at input entry {} after the maximum routes: {:?}.",
+                self.run_id, self.input_index, self.counts
+            );
+            return Ok(());
+        }
+        finish_verification_run(pool, self.run_id, &self.counts).await?;
+        info!(
+            "Finished verification run {}: {:?}.",
+            self.run_id, self.counts
+        );
+        Ok(())
+    }
+}
+
+/// Load the IR from `source` and record the start of a verification run
+/// with it on `input_file`, or continue the last unfinished one if resuming.
+async fn start_run(
+    pool: &Pool<Postgres>,
+    source: IrSource<'_>,
+    input_file: &str,
+    options: RecordOptions,
+) -> Result<ActiveRun> {
+    debug!("Loading IR.");
+    let (query, run) = source.load(pool, input_file).await?;
+
+    let unfinished = match options.resume {
+        true => find_unfinished_run(pool, &run).await?,
+        false => None,
+    };
+    let (run_id, counts, input_index, offset) = match unfinished {
+        Some(unfinished) => {
+            info!(
+                "Resuming verification run {} from input entry {}, record {:?}.",
+                unfinished.run_id, unfinished.checkpoint, unfinished.checkpoint_offset
+            );
+            (
+                unfinished.run_id,
+                unfinished.counts,
+                unfinished.checkpoint,
+                unfinished.checkpoint_offset,
+            )
+        }
+        None => {
+            if options.resume {
+                info!("No unfinished run on {input_file} to resume, starting a new one.");
+            }
+            let run_id = insert_verification_run(pool, &run).await?;
+            (run_id, RunCounts::default(), 0, None)
+        }
+    };
+    Ok(ActiveRun {
+        query,
+        run,
+        run_id,
+        counts,
+        input_index,
+        offset,
+        routes_left: options.max_routes,
+    })
+}
+
+/// Input entry with the byte offset of its record, if the input can seek.
+type OffsetItem<T> = (Option<u64>, Result<T>);
+
+/// Drain the iterator `open` returns on a blocking thread,
+/// skipping its first `skip` items,
+/// and sending the rest with the offsets of their records through a channel
+/// of [`LINE_BUFFER`].
+/// If the input seeks to the record at `from`, only the items from there
+/// to `skip` are decoded and skipped.
+/// Dropping the receiver stops the thread.
+fn spawn_reader<I, T>(
+    open: impl FnOnce(Option<u64>) -> Result<I> + Send + 'static,
+    skip: i64,
+    from: Option<InputOffset>,
+) -> (mpsc::Receiver<OffsetItem<T>>, JoinHandle<Result<()>>)
+where
+    I: InputEntries<Item = Result<T>>,
+    T: Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel(LINE_BUFFER);
+    let reader = spawn_blocking(move || -> Result<()> {
+        let mut entries = open(from.map(|from| from.offset))?;
+        let skip = match (from, entries.offset()) {
+            (Some(from), Some(_)) => skip - from.input_index,
+            _ => skip,
+        };
+        for _ in 0..skip {
+            if entries.next().is_none() {
+                break;
+            }
+        }
+        while let Some(item) = entries.next() {
+            if sender.blocking_send((entries.offset(), item)).is_err() {
+                // The writer stopped early.
+                break;
+            }
+        }
+        Ok(())
+    });
+    (receiver, reader)
+}
+
+/// Store `observed`, verifying `line` unless its route path was already
+/// verified against the same IR.
+async fn record_line(
+    pool: &Pool<Postgres>,
+    observed: &ObservedRoute,
+    line: &mut Line,
+    active: &mut ActiveRun,
+    input_index: i64,
+) -> Result<i32> {
+    let mut transaction = pool.begin().await?;
+    let path_verification_id = verify_path(&mut transaction, &observed.path, line, active).await?;
+    let observed_route_id = insert_observed_route(
+        &mut transaction,
+        observed,
+        path_verification_id,
+        active.run_id,
+        input_index,
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(observed_route_id)
+}
+
+/// Store `update`, verifying its announcement like [`record_line`].
+async fn record_update(
+    pool: &Pool<Postgres>,
+    update: &mut UpdateLine,
+    active: &mut ActiveRun,
+    input_index: i64,
+) -> Result<i32> {
+    let mut transaction = pool.begin().await?;
+    let path_verification_id = match &mut update.announcement {
+        Some(line) => {
+            let path = observed_route(line).path;
+            Some(verify_path(&mut transaction, &path, line, active).await?)
+        }
+        None => None,
+    };
+    let observed_route_id = insert_observed_update(
+        &mut transaction,
+        &update.update,
+        path_verification_id,
+        active.run_id,
+        input_index,
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(observed_route_id)
+}
+
+/// Copy `stored` into the run of `active`, verifying it like
+/// [`record_line`].
+async fn record_stored(
+    pool: &Pool<Postgres>,
+    stored: &StoredRoute,
+    active: &mut ActiveRun,
+    input_index: i64,
+) -> Result<i32> {
+    let mut transaction = pool.begin().await?;
+    let path_verification_id = match &stored.as_path {
+        Some(as_path) => {
+            let path = RoutePath {
+                address_prefix: stored.address_prefix,
+                as_path: as_path.clone(),
+            };
+            let mut line = stored_line(stored.raw_line.clone(), &path)?;
+            Some(verify_path(&mut transaction, &path, &mut line, active).await?)
+        }
+        None => None,
+    };
+    let observed_route_id = copy_observed_route(
+        &mut transaction,
+        stored.observed_route_id,
+        path_verification_id,
+        active.run_id,
+        input_index,
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(observed_route_id)
+}
+
+/// ID of the verification of `path` against the IR and settings of `active`,
+/// verifying `line` if there is none yet.
+async fn verify_path(
+    conn: &mut PgConnection,
+    path: &RoutePath,
+    line: &mut Line,
+    active: &mut ActiveRun,
+) -> Result<i32> {
+    let route_path_id = upsert_route_path(conn, path).await?;
+    let path_verification_id = match find_path_verification(conn, route_path_id, &active.run)
+        .await?
+    {
+        Some(path_verification_id) => {
+            active.counts.n_cache_hit += 1;
+            path_verification_id
+        }
+        None => {
+            let route = verify(line, &active.query);
+            let path_verification_id =
+                insert_path_verification(conn, route_path_id, &route, &active.run, active.run_id)
+                    .await?;
+            active.counts.n_exchange_report += route.reports.len() as i32;
+            path_verification_id
+        }
+    };
+    Ok(path_verification_id)
+}
+
+async fn upsert_route_path(conn: &mut PgConnection, path: &RoutePath) -> sqlx::Result<i32> {
+    let route_path_id = sqlx::query!(
+        r#"INSERT INTO route_path(content_hash, address_prefix, as_path)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (content_hash) DO UPDATE SET content_hash = EXCLUDED.content_hash
+        RETURNING route_path_id"#,
+        path.content_hash(),
+        path.address_prefix,
+        path.as_path,
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .route_path_id;
+    Ok(route_path_id)
+}
+
+async fn find_path_verification(
+    conn: &mut PgConnection,
+    route_path_id: i32,
+    run: &VerificationRun,
+) -> sqlx::Result<Option<i32>> {
+    sqlx::query_scalar!(
+        r#"SELECT path_verification_id FROM path_verification
+        WHERE route_path_id = $1 AND ir_dir_hash = $2 AND as_rel_hash = $3
+            AND verifier_version = $4 AND verbosity = $5"#,
+        route_path_id,
+        run.ir_dir_hash,
+        run.as_rel_hash,
+        run.verifier_version,
+        &run.verbosity,
+    )
+    .fetch_optional(&mut *conn)
+    .await
+}
+
+async fn insert_observed_route(
+    conn: &mut PgConnection,
+    observed: &ObservedRoute,
+    path_verification_id: i32,
+    run_id: i32,
+    input_index: i64,
+) -> sqlx::Result<i32> {
+    let observed_route_id = sqlx::query!(
+        r#"INSERT INTO observed_route(raw_line, address_prefix, path_verification, verification_run, input_index)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING observed_route_id"#,
+        observed.raw_line,
+        observed.path.address_prefix,
+        path_verification_id,
+        run_id,
+        input_index,
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .observed_route_id;
+    Ok(observed_route_id)
+}
+
+async fn insert_observed_update(
+    conn: &mut PgConnection,
+    update: &ObservedUpdate,
+    path_verification_id: Option<i32>,
+    run_id: i32,
+    input_index: i64,
+) -> sqlx::Result<i32> {
+    let observed_route_id = sqlx::query!(
+        r#"INSERT INTO observed_route(raw_line, address_prefix, path_verification, verification_run, input_index, withdrawal, observed_time, peer_ip, peer_as)
+        VALUES ($1, $2, $3, $4, $5, $6, to_timestamp($7), $8, $9)
+        RETURNING observed_route_id"#,
+        update.raw_line,
+        update.address_prefix,
+        path_verification_id,
+        run_id,
+        input_index,
+        update.withdrawal,
+        update.time(),
+        IpNetwork::from(update.peer_ip),
+        update.peer_as as i64,
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .observed_route_id;
+    Ok(observed_route_id)
+}
+
+/// Up to `limit` routes observed in run `run_id`,
+/// from position `input_index` in its input on.
+async fn fetch_stored_routes(
+    pool: &Pool<Postgres>,
+    run_id: i32,
+    input_index: i64,
+    limit: i64,
+) -> sqlx::Result<Vec<StoredRoute>> {
+    sqlx::query_as!(
+        StoredRoute,
+        r#"SELECT o.observed_route_id, o.input_index, o.raw_line, o.address_prefix, p.as_path AS "as_path?"
+        FROM observed_route o
+        LEFT JOIN path_verification v ON o.path_verification = v.path_verification_id
+        LEFT JOIN route_path p ON v.route_path_id = p.route_path_id
+        WHERE o.verification_run = $1 AND o.input_index >= $2
+        ORDER BY o.input_index
+        LIMIT $3"#,
+        run_id,
+        input_index,
+        limit,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Copy observed route `observed_route_id` into run `run_id`,
+/// pointing to `path_verification_id`.
+async fn copy_observed_route(
+    conn: &mut PgConnection,
+    observed_route_id: i32,
+    path_verification_id: Option<i32>,
+    run_id: i32,
+    input_index: i64,
+) -> sqlx::Result<i32> {
+    let observed_route_id = sqlx::query!(
+        r#"INSERT INTO observed_route(raw_line, address_prefix, path_verification, verification_run, input_index, withdrawal, observed_time, peer_ip, peer_as)
+        SELECT raw_line, address_prefix, $2, $3, $4, withdrawal, observed_time, peer_ip, peer_as
+        FROM observed_route
+        WHERE observed_route_id = $1
+        RETURNING observed_route_id"#,
+        observed_route_id,
+        path_verification_id,
+        run_id,
+        input_index,
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .observed_route_id;
+    Ok(observed_route_id)
+}
+
+async fn insert_path_verification(
+    conn: &mut PgConnection,
+    route_path_id: i32,
+    route: &VerifiedRoute,
+    run: &VerificationRun,
+    run_id: i32,
+) -> sqlx::Result<i32> {
+    let summary = route.summary();
+    let path_verification_id = sqlx::query!(
+        r#"INSERT INTO path_verification(route_path_id, ir_dir_hash, as_rel_hash, verifier_version, verbosity, verification_run, worst_type, n_ok, n_skip, n_unrecorded, n_special_case, n_bad, first_bad_hop, fully_verified)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+        RETURNING path_verification_id"#,
+        route_path_id,
+        run.ir_dir_hash,
+        run.as_rel_hash,
+        run.verifier_version,
+        &run.verbosity,
+        run_id,
+        summary.worst_type as _,
+        summary.n_ok,
+        summary.n_skip,
+        summary.n_unrecorded,
+        summary.n_special_case,
+        summary.n_bad,
+        summary.first_bad_hop.map(|hop| hop as i32),
+        summary.fully_verified,
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .path_verification_id;
+
+    for report in &route.reports {
+        _ = insert_exchange_report(&mut *conn, report, path_verification_id, run_id).await?;
+    }
+
+    Ok(path_verification_id)
+}
+
+async fn insert_exchange_report(
+    conn: &mut PgConnection,
+    report: &ExchangeReport,
+    path_verification_id: i32,
+    run_id: i32,
+) -> sqlx::Result<i32> {
+    let report_id = sqlx::query!(
+        r#"INSERT INTO exchange_report(from_as, to_as, import, overall_type, parent_verification, verification_run, report_index, hop_index)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING report_id"#,
+        report.from_as as i32,
+        report.to_as as i32,
+        report.import,
+        report.overall_type as _,
+        path_verification_id,
+        run_id,
+        report.report_index as i32,
+        report.hop_index.map(|index| index as i32),
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .report_id;
+
+    for item in &report.items {
+        insert_report_item(&mut *conn, report.overall_type, item, report_id).await?;
+    }
+
+    Ok(report_id)
+}
+
+async fn insert_report_item(
+    conn: &mut PgConnection,
+    category: OverallReportType,
+    item: &ReportItemRecord,
+    exchange_report_id: i32,
+) -> sqlx::Result<i32> {
+    // Insert the report item with its corresponding details
+    let report_item_id = sqlx::query!(
+        r#"INSERT INTO report_item(category, specific_case, str_content, num_content, parent_report)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING report_item_id"#,
+        category as _,
+        item.specific_case as _,
+        item.str_content,
+        item.num_content,
+        exchange_report_id
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .report_item_id;
+
+    Ok(report_item_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Executor, PgPool};
+
+    use super::*;
+
+    const FIXTURE_SOURCE: IrSource = IrSource::Files {
+        ir_dir: "fixtures/parsed_all",
+        as_rel_file: "fixtures/20230701.as-rel.bz2",
+    };
+    const FIXTURE_ROUTES_FILE: &str = "fixtures/routes.txt";
+
+    #[sqlx::test]
+    async fn capped_runs_stay_unfinished(pool: PgPool) -> Result<()> {
+        pool.execute(include_str!("../../demo_v1.sql")).await?;
+        pool.execute(include_str!("../../trigger_only.sql")).await?;
+        let options = RecordOptions {
+            resume: false,
+            max_routes: Some(2),
+        };
+        record_text_routes(&pool, FIXTURE_SOURCE, FIXTURE_ROUTES_FILE, options).await?;
+
+        let run = sqlx::query!(
+            r#"SELECT end_time IS NULL AS "unfinished!", checkpoint, n_observed_route
+            FROM verification_run"#
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert!(run.unfinished);
+        assert_eq!(run.checkpoint, 2);
+        assert_eq!(run.n_observed_route, 2);
+        Ok(())
+    }
+
+    /// Raw lines of the observed routes of the only run, in input order.
+    async fn stored_raw_lines(pool: &PgPool) -> Result<Vec<String>> {
+        let raw_lines = sqlx::query_scalar!(
+            "SELECT raw_line FROM observed_route ORDER BY input_index, observed_route_id"
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(raw_lines)
+    }
+
+    #[sqlx::test]
+    async fn resumed_runs_seek_to_checkpoint(pool: PgPool) -> Result<()> {
+        pool.execute(include_str!("../../demo_v1.sql")).await?;
+        pool.execute(include_str!("../../trigger_only.sql")).await?;
+        let capped = RecordOptions {
+            resume: false,
+            max_routes: Some(2),
+        };
+        record_text_routes(&pool, FIXTURE_SOURCE, FIXTURE_ROUTES_FILE, capped).await?;
+
+        let text = std::fs::read_to_string(FIXTURE_ROUTES_FILE)?;
+        let second_route = text.find("192.0.2.0/24").unwrap() as i64;
+        let run =
+            sqlx::query!("SELECT checkpoint_offset, checkpoint_offset_index FROM verification_run")
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(run.checkpoint_offset, Some(second_route));
+        assert_eq!(run.checkpoint_offset_index, Some(1));
+
+        let resume = RecordOptions {
+            resume: true,
+            max_routes: None,
+        };
+        record_text_routes(&pool, FIXTURE_SOURCE, FIXTURE_ROUTES_FILE, resume).await?;
+
+        let routes: Vec<_> = text.lines().filter(|line| line.contains('|')).collect();
+        assert_eq!(stored_raw_lines(&pool).await?, routes);
+        let run = sqlx::query!(
+            r#"SELECT end_time IS NULL AS "unfinished!", n_observed_route, n_failed
+            FROM verification_run"#
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert!(!run.unfinished);
+        assert_eq!(run.n_observed_route, routes.len() as i32);
+        assert_eq!(run.n_failed, 1);
+        Ok(())
+    }
+
+    #[cfg(feature = "native_mrt")]
+    #[sqlx::test]
+    async fn resumed_update_runs_seek_to_checkpoint(pool: PgPool) -> Result<()> {
+        const UPDATES_FILE: &str = "fixtures/updates.mrt";
+        pool.execute(include_str!("../../demo_v1.sql")).await?;
+        pool.execute(include_str!("../../trigger_only.sql")).await?;
+        let capped = RecordOptions {
+            resume: false,
+            max_routes: Some(5),
+        };
+        record_updates(&pool, FIXTURE_SOURCE, UPDATES_FILE, capped).await?;
+        // The fifth update is the first of the two in the record at byte 202.
+        let run =
+            sqlx::query!("SELECT checkpoint_offset, checkpoint_offset_index FROM verification_run")
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(run.checkpoint_offset, Some(202));
+        assert_eq!(run.checkpoint_offset_index, Some(4));
+
+        let resume = RecordOptions {
+            resume: true,
+            max_routes: None,
+        };
+        record_updates(&pool, FIXTURE_SOURCE, UPDATES_FILE, resume).await?;
+
+        let updates = stream_updates(UPDATES_FILE, None)?
+            .map(|update| Ok(update?.update.raw_line))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(stored_raw_lines(&pool).await?, updates);
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+//! The loader's own report model, independent of the upstream verifier.
+//! [`crate::adapter`] converts upstream reports into these,
+//! and only these reach the database.
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::types::ipnetwork::IpNetwork;
+
+use crate::report_type::{OverallReportType, ReportItemType};
+
+/// A route as read from a BGP dump.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ObservedRoute {
+    /// The line the route was read from.
+    pub raw_line: String,
+    pub path: RoutePath,
+}
+
+/// Route announcement or withdrawal read from a BGP update dump.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ObservedUpdate {
+    /// The line the update was read from.
+    pub raw_line: String,
+    pub address_prefix: IpNetwork,
+    /// Seconds since the Unix epoch when the collector received the update.
+    pub timestamp: u32,
+    /// Microseconds past `timestamp`, for dumps with extended timestamps.
+    pub microseconds: u32,
+    pub peer_ip: IpAddr,
+    pub peer_as: u32,
+    pub withdrawal: bool,
+}
+
+impl ObservedUpdate {
+    /// Seconds since the Unix epoch, including microseconds.
+    pub fn time(&self) -> f64 {
+        self.timestamp as f64 + self.microseconds as f64 / 1e6
+    }
+}
+
+/// Prefix and AS path of a route, which is all verification depends on.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RoutePath {
+    pub address_prefix: IpNetwork,
+    /// AS path with prepending removed, AS sets in braces.
+    pub as_path: String,
+}
+
+impl RoutePath {
+    /// SHA-256 identifying the prefix and AS path.
+    pub fn content_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.address_prefix.to_string());
+        hasher.update("|");
+        hasher.update(&self.as_path);
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Reports generated on a [`RoutePath`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VerifiedRoute {
+    /// Number of hops involving AS sets, which the verifier cannot check.
+    pub set_hops: u32,
+    pub reports: Vec<ExchangeReport>,
+}
+
+impl VerifiedRoute {
+    pub fn summary(&self) -> RouteSummary {
+        let mut summary = RouteSummary {
+            worst_type: None,
+            n_ok: 0,
+            n_skip: 0,
+            n_unrecorded: 0,
+            n_special_case: 0,
+            n_bad: 0,
+            first_bad_hop: None,
+            fully_verified: self.set_hops == 0,
+        };
+        for report in &self.reports {
+            summary.worst_type = summary.worst_type.max(Some(report.overall_type));
+            match report.overall_type {
+                OverallReportType::Ok => summary.n_ok += 1,
+                OverallReportType::Skip => summary.n_skip += 1,
+                OverallReportType::Unrecorded => summary.n_unrecorded += 1,
+                OverallReportType::SpecialCase => summary.n_special_case += 1,
+                OverallReportType::Bad => {
+                    summary.n_bad += 1;
+                    if summary.first_bad_hop.is_none() {
+                        summary.first_bad_hop = report.hop_index;
+                    }
+                }
+            }
+            if report.overall_type != OverallReportType::Ok {
+                summary.fully_verified = false;
+            }
+        }
+        summary
+    }
+}
+
+/// Aggregate verdict over all [`ExchangeReport`]s of a route.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RouteSummary {
+    /// `None` if the route has no reports, e.g., its AS path has one AS.
+    pub worst_type: Option<OverallReportType>,
+    pub n_ok: i32,
+    pub n_skip: i32,
+    pub n_unrecorded: i32,
+    pub n_special_case: i32,
+    pub n_bad: i32,
+    /// Hop of the first bad report, in the order the route propagated.
+    pub first_bad_hop: Option<u32>,
+    /// Whether every hop was checked and all reports are ok.
+    pub fully_verified: bool,
+}
+
+/// Report on the import or export of the route between two ASes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExchangeReport {
+    pub from_as: u32,
+    pub to_as: u32,
+    pub import: bool,
+    pub overall_type: OverallReportType,
+    /// Position among the reports the verifier generated for the route.
+    pub report_index: u32,
+    /// Position of `from_as` in the AS path, with prepending removed.
+    /// `None` if the verifier reported a pair not found in the path.
+    pub hop_index: Option<u32>,
+    pub items: Vec<ReportItemRecord>,
+}
+
+/// Single detail of an [`ExchangeReport`].
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Deserialize, Serialize)]
+pub struct ReportItemRecord {
+    pub specific_case: ReportItemType,
+    pub str_content: Option<String>,
+    pub num_content: Option<i32>,
+}
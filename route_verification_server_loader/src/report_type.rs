@@ -0,0 +1,179 @@
+//! Postgres enums for reports, defined once here.
+//! `demo_v1.sql` embeds the output of [`OverallReportType::create_type_sql`]
+//! and [`ReportItemType::create_type_sql`]; run `cargo r -- schema` to
+//! regenerate them and `cargo r -- check-schema` to diff them against the
+//! database.
+
+/// Define a Rust enum mirroring a Postgres enum,
+/// with each variant spelled out with its database label,
+/// which is also its `serde` name.
+/// Variants compare in declaration order, like Postgres enum values.
+macro_rules! sql_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident as $type_name:literal {
+            $($variant:ident => $label:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(
+            Copy,
+            Clone,
+            Debug,
+            Eq,
+            Hash,
+            Ord,
+            PartialEq,
+            PartialOrd,
+            serde::Deserialize,
+            serde::Serialize,
+            sqlx::Type,
+        )]
+        #[sqlx(type_name = $type_name)]
+        $vis enum $name {
+            $(#[sqlx(rename = $label)] #[serde(rename = $label)] $variant,)*
+        }
+
+        impl $name {
+            /// Name of the Postgres enum type.
+            pub const TYPE_NAME: &'static str = $type_name;
+
+            /// Every variant, in the order the Postgres enum declares them.
+            pub const ALL: &'static [Self] = &[$(Self::$variant,)*];
+
+            /// Label of this variant in the Postgres enum.
+            pub const fn as_str(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $label,)*
+                }
+            }
+
+            /// Variant labeled `label` in the Postgres enum.
+            pub fn from_label(label: &str) -> Option<Self> {
+                Self::ALL.iter().copied().find(|v| v.as_str() == label)
+            }
+
+            /// `create type` statement for the Postgres enum.
+            pub fn create_type_sql() -> String {
+                create_type_sql(Self::TYPE_NAME, Self::ALL.iter().map(|v| v.as_str()))
+            }
+        }
+    };
+}
+
+fn create_type_sql<'a>(type_name: &str, labels: impl Iterator<Item = &'a str>) -> String {
+    let labels: Vec<_> = labels.map(|label| format!("\t'{label}'")).collect();
+    format!(
+        "create type {type_name} as enum (\n{}\n);",
+        labels.join(",\n")
+    )
+}
+
+sql_enum! {
+    /// Ordered from best to worst.
+    pub enum OverallReportType as "overall_report_type" {
+        Ok => "ok",
+        Skip => "skip",
+        Unrecorded => "unrecorded",
+        SpecialCase => "special_case",
+        Bad => "bad",
+    }
+}
+
+sql_enum! {
+    pub enum ReportItemType as "report_item_type" {
+        SkipRegexTilde => "skip_regex_tilde",
+        SkipRegexWithSet => "skip_regex_with_set",
+        SkipCommunity => "skip_community",
+        UnrecImportEmpty => "unrec_import_empty",
+        UnrecExportEmpty => "unrec_export_empty",
+        UnrecFilterSet => "unrec_filter_set",
+        UnrecAsRoutes => "unrec_as_routes",
+        UnrecRouteSet => "unrec_route_set",
+        UnrecAsSet => "unrec_as_set",
+        UnrecAsSetRoute => "unrec_as_set_route",
+        UnrecSomeAsSetRoute => "unrec_some_as_set_route",
+        UnrecAutNum => "unrec_aut_num",
+        UnrecPeeringSet => "unrec_peering_set",
+        SpecUphill => "spec_uphill",
+        SpecUphillTier1 => "spec_uphill_tier1",
+        SpecTier1Pair => "spec_tier1_pair",
+        SpecImportPeerOIFPS => "spec_import_peer_oifps",
+        SpecImportCustomerOIFPS => "spec_import_customer_oifps",
+        SpecExportCustomers => "spec_export_customers",
+        SpecImportFromNeighbor => "spec_import_from_neighbor",
+        SpecAsIsOriginButNoRoute => "spec_as_is_origin_but_no_route",
+        SpecAsSetContainsOriginButNoRoute => "spec_as_set_contains_origin_but_no_route",
+        ErrFilter => "err_filter",
+        ErrFilterAsNum => "err_filter_as_num",
+        ErrFilterAsSet => "err_filter_as_set",
+        ErrFilterPrefixes => "err_filter_prefixes",
+        ErrFilterRouteSet => "err_filter_route_set",
+        ErrRemoteAsNum => "err_remote_as_num",
+        ErrRemoteAsSet => "err_remote_as_set",
+        ErrExceptPeeringRight => "err_except_peering_right",
+        ErrPeering => "err_peering",
+        ErrRegex => "err_regex",
+        RpslAsName => "rpsl_as_name",
+        RpslFilter => "rpsl_filter",
+        RpslRegex => "rpsl_regex",
+        RpslUnknownFilter => "rpsl_unknown_filter",
+        RecCheckFilter => "rec_check_filter",
+        RecFilterRouteSet => "rec_filter_route_set",
+        RecFilterRouteSetMember => "rec_filter_route_set_member",
+        RecFilterAsSet => "rec_filter_as_set",
+        RecFilterAsName => "rec_filter_as_name",
+        RecFilterAnd => "rec_filter_and",
+        RecFilterOr => "rec_filter_or",
+        RecFilterNot => "rec_filter_not",
+        RecCheckSetMember => "rec_check_set_member",
+        RecCheckRemoteAs => "rec_check_remote_as",
+        RecRemoteAsName => "rec_remote_as_name",
+        RecRemoteAsSet => "rec_remote_as_set",
+        RecRemotePeeringSet => "rec_remote_peering_set",
+        RecPeeringAnd => "rec_peering_and",
+        RecPeeringOr => "rec_peering_or",
+        RecPeeringExcept => "rec_peering_except",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const SCHEMA: &str = include_str!("../../demo_v1.sql");
+
+    #[test]
+    fn labels_are_unique() {
+        for all in [
+            ReportItemType::ALL
+                .iter()
+                .map(|v| v.as_str())
+                .collect::<Vec<_>>(),
+            OverallReportType::ALL.iter().map(|v| v.as_str()).collect(),
+        ] {
+            let unique: HashSet<_> = all.iter().collect();
+            assert_eq!(unique.len(), all.len());
+        }
+        assert_eq!(
+            ReportItemType::from_label("err_peering"),
+            Some(ReportItemType::ErrPeering)
+        );
+        assert_eq!(OverallReportType::from_label("worse"), None);
+    }
+
+    #[test]
+    fn schema_matches_enums() {
+        for create_type in [
+            OverallReportType::create_type_sql(),
+            ReportItemType::create_type_sql(),
+        ] {
+            assert!(
+                SCHEMA.contains(&create_type),
+                "`demo_v1.sql` is outdated, run `cargo r -- schema` and replace the enum with:\n{create_type}"
+            );
+        }
+    }
+}
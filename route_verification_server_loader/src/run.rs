@@ -0,0 +1,265 @@
+//! Bookkeeping for each verification run, so reports generated with
+//! different inputs or settings can be told apart.
+use std::{
+    fs::{read_dir, File},
+    io,
+    path::Path,
+};
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
+
+use crate::adapter::{verbosity_flags, VERIFIER_VERSION};
+
+/// Inputs and settings of a verification run.
+#[derive(Clone, Debug)]
+pub struct VerificationRun {
+    pub verbosity: Vec<String>,
+    pub ir_dir_hash: String,
+    pub as_rel_file: String,
+    pub as_rel_hash: String,
+    pub input_file: String,
+    pub verifier_version: String,
+    pub loader_version: String,
+}
+
+impl VerificationRun {
+    /// Describe a run verifying the routes in `input_file` with the IR in
+    /// `ir_dir` and the AS relationships in `as_rel_file`.
+    /// Hashes every file in `ir_dir` and `as_rel_file`.
+    pub fn new(ir_dir: &str, as_rel_file: &str, input_file: &str) -> Result<Self> {
+        Ok(Self::with_hashes(
+            hash_dir(ir_dir)?,
+            as_rel_file,
+            hash_file(as_rel_file)?,
+            input_file,
+        ))
+    }
+
+    /// Describe a run on `input_file` with an IR and AS relationships
+    /// hashed elsewhere, e.g., read from the database.
+    pub fn with_hashes(
+        ir_dir_hash: String,
+        as_rel_file: &str,
+        as_rel_hash: String,
+        input_file: &str,
+    ) -> Self {
+        Self {
+            verbosity: verbosity_flags().into_iter().map(Into::into).collect(),
+            ir_dir_hash,
+            as_rel_file: as_rel_file.into(),
+            as_rel_hash,
+            input_file: input_file.into(),
+            verifier_version: VERIFIER_VERSION.into(),
+            loader_version: env!("CARGO_PKG_VERSION").into(),
+        }
+    }
+}
+
+/// Counts accumulated over a verification run.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RunCounts {
+    pub n_observed_route: i32,
+    pub n_exchange_report: i32,
+    pub n_cache_hit: i32,
+    pub n_failed: i32,
+}
+
+/// Run that was interrupted before finishing.
+#[derive(Clone, Copy, Debug)]
+pub struct UnfinishedRun {
+    pub run_id: i32,
+    /// Counts as of the last checkpoint,
+    /// except `n_observed_route`, which counts every stored route.
+    pub counts: RunCounts,
+    /// Position in the input to continue from.
+    pub checkpoint: i64,
+    /// Record at or before `checkpoint` to seek to, if the input can seek.
+    pub checkpoint_offset: Option<InputOffset>,
+}
+
+/// Record in a seekable input.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InputOffset {
+    /// Byte offset the record starts at.
+    pub offset: u64,
+    /// Position in the input of the first entry decoded from the record.
+    pub input_index: i64,
+}
+
+/// Input read one entry at a time that can tell where each entry's record
+/// starts, so a resumed run can seek back to it.
+/// Opening the input at an offset seeks there if the input can seek.
+pub trait InputEntries: Iterator {
+    /// Byte offset of the record the last entry came from,
+    /// or where reading starts before the first entry.
+    /// `None` if the input cannot seek.
+    fn offset(&self) -> Option<u64>;
+}
+
+/// SHA-256 over the names and contents of the files in `dir`,
+/// in name order.
+fn hash_dir<P: AsRef<Path>>(dir: P) -> Result<String> {
+    let mut paths = read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.sort();
+
+    let mut hasher = Sha256::new();
+    for path in paths {
+        if let Some(name) = path.file_name() {
+            hasher.update(name.as_encoded_bytes());
+        }
+        io::copy(&mut File::open(&path)?, &mut hasher)?;
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// SHA-256 of the contents of the file at `path`.
+pub fn hash_file<P: AsRef<Path>>(path: P) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+pub async fn insert_verification_run(
+    pool: &Pool<Postgres>,
+    run: &VerificationRun,
+) -> sqlx::Result<i32> {
+    let run_id = sqlx::query!(
+        r#"INSERT INTO verification_run(verbosity, ir_dir_hash, as_rel_file, as_rel_hash, input_file, verifier_version, loader_version)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING run_id"#,
+        &run.verbosity,
+        run.ir_dir_hash,
+        run.as_rel_file,
+        run.as_rel_hash,
+        run.input_file,
+        run.verifier_version,
+        run.loader_version,
+    )
+    .fetch_one(pool)
+    .await?
+    .run_id;
+    Ok(run_id)
+}
+
+pub async fn finish_verification_run(
+    pool: &Pool<Postgres>,
+    run_id: i32,
+    counts: &RunCounts,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"UPDATE verification_run
+        SET end_time = now(), n_observed_route = $2, n_exchange_report = $3, n_cache_hit = $4, n_failed = $5
+        WHERE run_id = $1"#,
+        run_id,
+        counts.n_observed_route,
+        counts.n_exchange_report,
+        counts.n_cache_hit,
+        counts.n_failed,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Latest unfinished run with the same inputs and settings as `run`.
+/// Its checkpoint is moved past every route it stored after the last
+/// saved checkpoint, so resuming never stores a route twice.
+pub async fn find_unfinished_run(
+    pool: &Pool<Postgres>,
+    run: &VerificationRun,
+) -> sqlx::Result<Option<UnfinishedRun>> {
+    let row = sqlx::query!(
+        r#"SELECT
+            run_id,
+            (SELECT count(*) FROM observed_route WHERE verification_run = run_id)::int AS "n_observed_route!",
+            n_exchange_report,
+            n_cache_hit,
+            n_failed,
+            GREATEST(
+                checkpoint,
+                (SELECT max(input_index) + 1 FROM observed_route WHERE verification_run = run_id)
+            ) AS "checkpoint!",
+            checkpoint_offset,
+            checkpoint_offset_index
+        FROM verification_run
+        WHERE end_time IS NULL
+            AND verbosity = $1
+            AND ir_dir_hash = $2
+            AND as_rel_file = $3
+            AND as_rel_hash = $4
+            AND input_file = $5
+            AND verifier_version = $6
+            AND loader_version = $7
+        ORDER BY run_id DESC
+        LIMIT 1"#,
+        &run.verbosity,
+        run.ir_dir_hash,
+        run.as_rel_file,
+        run.as_rel_hash,
+        run.input_file,
+        run.verifier_version,
+        run.loader_version,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|row| UnfinishedRun {
+        run_id: row.run_id,
+        counts: RunCounts {
+            n_observed_route: row.n_observed_route,
+            n_exchange_report: row.n_exchange_report,
+            n_cache_hit: row.n_cache_hit,
+            n_failed: row.n_failed,
+        },
+        checkpoint: row.checkpoint,
+        checkpoint_offset: match (row.checkpoint_offset, row.checkpoint_offset_index) {
+            (Some(offset), Some(input_index)) => Some(InputOffset {
+                offset: offset as u64,
+                input_index,
+            }),
+            _ => None,
+        },
+    }))
+}
+
+/// Record that the first `checkpoint` input entries of run `run_id` are
+/// processed, with the counts so far.
+/// `checkpoint_offset` is a record of a seekable input
+/// holding entry `checkpoint` or one before.
+pub async fn save_checkpoint(
+    pool: &Pool<Postgres>,
+    run_id: i32,
+    checkpoint: i64,
+    checkpoint_offset: Option<InputOffset>,
+    counts: &RunCounts,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"UPDATE verification_run
+        SET checkpoint = $2, checkpoint_offset = $3, checkpoint_offset_index = $4,
+            n_observed_route = $5, n_exchange_report = $6, n_cache_hit = $7, n_failed = $8
+        WHERE run_id = $1"#,
+        run_id,
+        checkpoint,
+        checkpoint_offset.map(|offset| offset.offset as i64),
+        checkpoint_offset.map(|offset| offset.input_index),
+        counts.n_observed_route,
+        counts.n_exchange_report,
+        counts.n_cache_hit,
+        counts.n_failed,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// ID of the latest verification run that finished.
+pub async fn latest_finished_run(pool: &Pool<Postgres>) -> sqlx::Result<Option<i32>> {
+    sqlx::query_scalar!(
+        "SELECT run_id FROM verification_run WHERE end_time IS NOT NULL ORDER BY run_id DESC LIMIT 1"
+    )
+    .fetch_optional(pool)
+    .await
+}
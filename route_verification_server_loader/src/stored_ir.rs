@@ -0,0 +1,277 @@
+//! Read the IR and AS relationships back from the database,
+//! so verification can run on a host with only database access.
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use log::{debug, info};
+use sha2::{Digest, Sha256};
+use sqlx::{types::Json, Pool, Postgres};
+
+use crate::{adapter::*, run::VerificationRun};
+
+/// Where a verification run gets its IR and AS relationships.
+#[derive(Clone, Copy, Debug)]
+pub enum IrSource<'a> {
+    /// IR JSON files in `ir_dir` and the CAIDA file `as_rel_file`.
+    Files {
+        ir_dir: &'a str,
+        as_rel_file: &'a str,
+    },
+    /// The loaded IR and the latest AS relationship snapshot in the database.
+    Database,
+}
+
+impl IrSource<'_> {
+    /// Load the IR and AS relationships, and describe a run with them
+    /// on `input_file`.
+    pub async fn load(
+        &self,
+        pool: &Pool<Postgres>,
+        input_file: &str,
+    ) -> Result<(QueryIr, VerificationRun)> {
+        match *self {
+            Self::Files {
+                ir_dir,
+                as_rel_file,
+            } => {
+                let db = AsRelDb::load_bz(as_rel_file)?;
+                let parsed = Ir::pal_read(ir_dir)?;
+                let query = QueryIr::from_ir_and_as_relationship(parsed, &db);
+                let run = VerificationRun::new(ir_dir, as_rel_file, input_file)?;
+                Ok((query, run))
+            }
+            Self::Database => {
+                let (db, as_rel_file, as_rel_hash) = read_as_rel_db(pool).await?;
+                let parsed = read_ir(pool).await?;
+                // The IR read back hashes differently from its files,
+                // so runs on either source do not share verifications.
+                let ir_hash = hash_ir(&parsed)?;
+                let query = QueryIr::from_ir_and_as_relationship(parsed, &db);
+                let run =
+                    VerificationRun::with_hashes(ir_hash, &as_rel_file, as_rel_hash, input_file);
+                Ok((query, run))
+            }
+        }
+    }
+}
+
+/// Reconstruct the IR `load` stored.
+pub async fn read_ir(pool: &Pool<Postgres>) -> Result<Ir> {
+    debug!("Reading aut-nums.");
+    let aut_nums = sqlx::query!(
+        r#"SELECT
+            a.as_num,
+            o.body,
+            a.imports AS "imports: Json<Versions>",
+            a.exports AS "exports: Json<Versions>"
+        FROM aut_num a
+        JOIN rpsl_obj o ON o.rpsl_obj_name = a.rpsl_obj_name"#
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| {
+        let aut_num = AutNum {
+            body: row.body,
+            imports: row.imports.0,
+            exports: row.exports.0,
+        };
+        (row.as_num as u32, aut_num)
+    })
+    .collect();
+
+    debug!("Reading as-sets.");
+    let as_sets = sqlx::query!(
+        r#"SELECT
+            s.as_set_name,
+            o.body,
+            s.is_any,
+            ARRAY(
+                SELECT as_num FROM as_set_contains_num n
+                WHERE n.as_set_name = s.as_set_name ORDER BY as_num
+            ) AS "members!",
+            ARRAY(
+                SELECT contained_set FROM as_set_contains_set c
+                WHERE c.as_set_name = s.as_set_name ORDER BY contained_set
+            ) AS "set_members!"
+        FROM as_set s
+        JOIN rpsl_obj o ON o.rpsl_obj_name = s.as_set_name"#
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| {
+        let as_set = AsSet {
+            body: row.body,
+            members: row.members.into_iter().map(|num| num as u32).collect(),
+            set_members: row.set_members,
+            is_any: row.is_any,
+        };
+        (row.as_set_name, as_set)
+    })
+    .collect();
+
+    debug!("Reading route-sets.");
+    let route_sets = sqlx::query!(
+        r#"SELECT s.route_set_name, o.body, s.members AS "members: Json<Vec<RouteSetMember>>"
+        FROM route_set s
+        JOIN rpsl_obj o ON o.rpsl_obj_name = s.route_set_name"#
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| {
+        let route_set = RouteSet {
+            body: row.body,
+            members: row.members.0,
+        };
+        (row.route_set_name, route_set)
+    })
+    .collect();
+
+    debug!("Reading peering-sets.");
+    let peering_sets = sqlx::query!(
+        r#"SELECT s.peering_set_name, o.body, s.peerings AS "peerings: Json<Vec<Peering>>"
+        FROM peering_set s
+        JOIN rpsl_obj o ON o.rpsl_obj_name = s.peering_set_name"#
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| {
+        let peering_set = PeeringSet {
+            body: row.body,
+            peerings: row.peerings.0,
+        };
+        (row.peering_set_name, peering_set)
+    })
+    .collect();
+
+    debug!("Reading filter-sets.");
+    let filter_sets = sqlx::query!(
+        r#"SELECT s.filter_set_name, o.body, s.filters AS "filters: Json<Vec<Filter>>"
+        FROM filter_set s
+        JOIN rpsl_obj o ON o.rpsl_obj_name = s.filter_set_name"#
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| {
+        let filter_set = FilterSet {
+            body: row.body,
+            filters: row.filters.0,
+        };
+        (row.filter_set_name, filter_set)
+    })
+    .collect();
+
+    debug!("Reading AS routes.");
+    let mut as_routes: BTreeMap<u32, Vec<_>> = BTreeMap::new();
+    for row in
+        sqlx::query!("SELECT as_num, address_prefix FROM as_route ORDER BY as_num, route_index")
+            .fetch_all(pool)
+            .await?
+    {
+        let prefix = row.address_prefix.to_string().parse()?;
+        as_routes.entry(row.as_num as u32).or_default().push(prefix);
+    }
+
+    let ir = Ir {
+        aut_nums,
+        as_sets,
+        route_sets,
+        peering_sets,
+        filter_sets,
+        as_routes,
+    };
+    info!("Read the IR from the database: {ir}");
+    Ok(ir)
+}
+
+/// AS relationships of the latest snapshot,
+/// with the name and hash of the file they came from.
+pub async fn read_as_rel_db(pool: &Pool<Postgres>) -> Result<(AsRelDb, String, String)> {
+    let source = sqlx::query!(
+        r#"SELECT as_rel_source_id, file_name, file_hash FROM as_rel_source
+        ORDER BY snapshot_date DESC, as_rel_source_id DESC
+        LIMIT 1"#
+    )
+    .fetch_optional(pool)
+    .await?
+    .context("No AS relationship snapshot loaded")?;
+
+    // Rebuild the file's lines for the upstream parser.
+    let clique = sqlx::query_scalar!(
+        "SELECT as_num FROM tier1 WHERE as_rel_source = $1",
+        source.as_rel_source_id
+    )
+    .fetch_all(pool)
+    .await?;
+    // AS numbers above 2^31 are stored wrapped into `int`.
+    let clique: Vec<_> = clique
+        .iter()
+        .map(|&as_num| (as_num as u32).to_string())
+        .collect();
+    let mut lines = vec![format!("# input clique: {}", clique.join(" "))];
+    for row in sqlx::query!(
+        "SELECT provider, customer FROM provide_customer WHERE as_rel_source = $1",
+        source.as_rel_source_id
+    )
+    .fetch_all(pool)
+    .await?
+    {
+        lines.push(format!(
+            "{}|{}|-1",
+            row.provider as u32, row.customer as u32
+        ));
+    }
+    for row in sqlx::query!(
+        "SELECT peer_1, peer_2 FROM peer WHERE as_rel_source = $1",
+        source.as_rel_source_id
+    )
+    .fetch_all(pool)
+    .await?
+    {
+        lines.push(format!("{}|{}|0", row.peer_1 as u32, row.peer_2 as u32));
+    }
+
+    let db = AsRelDb::from_lines(lines)?;
+    Ok((db, source.file_name, source.file_hash))
+}
+
+/// SHA-256 of `ir` serialized as JSON.
+pub fn hash_ir(ir: &Ir) -> Result<String> {
+    let mut hasher = Sha256::new();
+    serde_json::to_writer(&mut hasher, ir)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Executor, PgPool};
+
+    use super::*;
+    use crate::as_rel::as_relationship_db;
+
+    const FIXTURE_AS_REL_FILE: &str = "fixtures/20230701.as-rel.bz2";
+
+    /// Needs the Postgres server at `DATABASE_URL`
+    /// to create a scratch database on.
+    #[sqlx::test]
+    async fn as_rel_round_trips(pool: PgPool) -> Result<()> {
+        pool.execute(include_str!("../../demo_v1.sql")).await?;
+        pool.execute(include_str!("../../trigger_only.sql")).await?;
+        as_relationship_db(&pool, FIXTURE_AS_REL_FILE).await?;
+
+        let file_db = AsRelDb::load_bz(FIXTURE_AS_REL_FILE)?;
+        let (db, file_name, _) = read_as_rel_db(&pool).await?;
+        assert_eq!(file_name, "20230701.as-rel.bz2");
+        assert_eq!(db.clique, file_db.clique);
+        assert_eq!(db.source2dest.len(), file_db.source2dest.len());
+        for &(from, to) in file_db.source2dest.keys() {
+            assert_eq!(db.get(from, to), file_db.get(from, to));
+        }
+        assert_eq!(db.get(4200000000, 3356), Some(Relationship::C2P));
+        Ok(())
+    }
+}
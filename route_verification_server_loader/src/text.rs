@@ -0,0 +1,229 @@
+//! Routes exported as plain text, e.g., by routers and looking glasses.
+//! Each line holds a prefix and an AS path,
+//! in a format picked by the file extension:
+//! - `.csv`: `prefix,as_path`, optionally below a header row.
+//! - `.json`, `.jsonl`, `.ndjson`: one object per line, e.g.,
+//!   `{"prefix": "203.0.113.0/24", "as_path": "64496 13335"}`.
+//!   `as_path` may also be an array of AS numbers, with arrays for AS sets.
+//! - Anything else: `prefix|as_path`.
+//!
+//! AS paths in text are space-separated, with AS sets in braces like
+//! `bgpdump` prints them.
+//! Blank lines and lines starting with `#` are skipped.
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+/// Layout of a text export.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TextFormat {
+    Pipe,
+    Csv,
+    Json,
+}
+
+impl TextFormat {
+    pub fn of_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("csv") => Self::Csv,
+            Some("json" | "jsonl" | "ndjson") => Self::Json,
+            _ => Self::Pipe,
+        }
+    }
+}
+
+/// Route read from a text export, not yet validated.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TextRoute {
+    /// The line the route was read from.
+    pub raw: String,
+    pub prefix: String,
+    pub as_path: String,
+}
+
+/// Routes in the text export at `path`, one line at a time,
+/// starting from the line at byte `offset` if given.
+pub fn read_text_routes<P: AsRef<Path>>(path: P, offset: Option<u64>) -> Result<TextRoutes> {
+    let format = TextFormat::of_path(&path);
+    let mut file = File::open(path)?;
+    let offset = offset.unwrap_or(0);
+    file.seek(SeekFrom::Start(offset))?;
+    Ok(TextRoutes {
+        format,
+        reader: BufReader::new(file),
+        position: offset,
+        line_offset: offset,
+    })
+}
+
+/// Iterator from [`read_text_routes`].
+pub struct TextRoutes {
+    format: TextFormat,
+    reader: BufReader<File>,
+    /// Byte offset of the next line.
+    position: u64,
+    /// Byte offset of the line the last route came from.
+    line_offset: u64,
+}
+
+impl TextRoutes {
+    /// Byte offset of the line the last route came from,
+    /// or where reading starts before the first route.
+    pub fn offset(&self) -> u64 {
+        self.line_offset
+    }
+}
+
+impl Iterator for TextRoutes {
+    type Item = Result<TextRoute>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line_offset = self.position;
+            let mut line = Vec::new();
+            match self.reader.read_until(b'\n', &mut line) {
+                Ok(0) => return None,
+                Ok(len) => self.position += len as u64,
+                Err(why) => return Some(Err(why.into())),
+            }
+            if line.ends_with(b"\n") {
+                line.pop();
+                if line.ends_with(b"\r") {
+                    line.pop();
+                }
+            }
+            let route = String::from_utf8(line)
+                .map_err(Into::into)
+                .and_then(|line| parse_text_route(self.format, line));
+            if let Some(route) = route.transpose() {
+                return Some(route);
+            }
+        }
+    }
+}
+
+/// Parse `raw` in `format`.
+/// `None` for blank lines, comments, and CSV headers.
+pub fn parse_text_route(format: TextFormat, raw: String) -> Result<Option<TextRoute>> {
+    let line = raw.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let (prefix, as_path) = match format {
+        TextFormat::Pipe => {
+            let (prefix, as_path) = line.split_once('|').context("missing `|`")?;
+            (prefix.to_owned(), as_path.to_owned())
+        }
+        TextFormat::Csv => {
+            let (prefix, as_path) = line.split_once(',').context("missing `,`")?;
+            let prefix = unquote(prefix);
+            if prefix.eq_ignore_ascii_case("prefix") {
+                return Ok(None);
+            }
+            (prefix.to_owned(), unquote(as_path).to_owned())
+        }
+        TextFormat::Json => {
+            let value: Value = serde_json::from_str(line)?;
+            let prefix = value["prefix"].as_str().context("missing `prefix`")?;
+            (prefix.to_owned(), json_as_path(&value["as_path"])?)
+        }
+    };
+    let as_path = as_path.split_whitespace().collect::<Vec<_>>().join(" ");
+    Ok(Some(TextRoute {
+        prefix: prefix.trim().to_owned(),
+        as_path,
+        raw,
+    }))
+}
+
+fn unquote(field: &str) -> &str {
+    let field = field.trim();
+    field
+        .strip_prefix('"')
+        .and_then(|field| field.strip_suffix('"'))
+        .unwrap_or(field)
+}
+
+fn json_as_path(value: &Value) -> Result<String> {
+    let entry = |value: &Value| -> Result<String> {
+        match value {
+            Value::Number(num) => Ok(num.to_string()),
+            Value::Array(nums) => {
+                let nums = nums
+                    .iter()
+                    .map(|num| num.as_u64().map(|num| num.to_string()))
+                    .collect::<Option<Vec<_>>>()
+                    .context("AS set with non-numbers")?;
+                Ok(format!("{{{}}}", nums.join(",")))
+            }
+            _ => bail!("unexpected AS path entry {value}"),
+        }
+    };
+    match value {
+        Value::String(as_path) => Ok(as_path.clone()),
+        Value::Array(entries) => Ok(entries
+            .iter()
+            .map(entry)
+            .collect::<Result<Vec<_>>>()?
+            .join(" ")),
+        _ => bail!("missing `as_path`"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(format: TextFormat, raw: &str) -> Option<(String, String)> {
+        parse_text_route(format, raw.into())
+            .unwrap()
+            .map(|route| (route.prefix, route.as_path))
+    }
+
+    fn expected(prefix: &str, as_path: &str) -> Option<(String, String)> {
+        Some((prefix.into(), as_path.into()))
+    }
+
+    #[test]
+    fn formats() {
+        let route = expected("203.0.113.0/24", "64496 3356 {65001,65002}");
+        assert_eq!(
+            parse(TextFormat::Pipe, "203.0.113.0/24|64496  3356 {65001,65002}"),
+            route
+        );
+        assert_eq!(
+            parse(
+                TextFormat::Csv,
+                r#"203.0.113.0/24,"64496 3356 {65001,65002}""#
+            ),
+            route
+        );
+        assert_eq!(
+            parse(
+                TextFormat::Json,
+                r#"{"prefix": "203.0.113.0/24", "as_path": [64496, 3356, [65001, 65002]]}"#
+            ),
+            route
+        );
+        assert_eq!(
+            parse(
+                TextFormat::Json,
+                r#"{"prefix": "2001:db8::/32", "as_path": "64496 6939"}"#
+            ),
+            expected("2001:db8::/32", "64496 6939")
+        );
+    }
+
+    #[test]
+    fn skipped_lines() {
+        assert_eq!(parse(TextFormat::Pipe, "  "), None);
+        assert_eq!(parse(TextFormat::Pipe, "# prefix|as_path"), None);
+        assert_eq!(parse(TextFormat::Csv, "prefix,as_path"), None);
+        assert!(parse_text_route(TextFormat::Pipe, "203.0.113.0/24 64496".into()).is_err());
+    }
+}
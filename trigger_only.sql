@@ -154,7 +154,7 @@ FOR EACH ROW
 EXECUTE FUNCTION check_prdcst_before_insert_autosys();
 --**************************************************************
 --check route_obj before insert autonomous_system
-CREATE OR REPLACE FUNCTION check_aut_num_before_insert_autosys()
+CREATE OR REPLACE FUNCTION check_route_obj_before_insert_autosys()
 RETURNS TRIGGER AS $$
 BEGIN
   IF NOT EXISTS (SELECT 1 FROM autonomous_system WHERE as_num = NEW.origin) THEN
@@ -167,7 +167,7 @@ $$ LANGUAGE plpgsql;
 CREATE TRIGGER trigger_before_insert_route_obj_autosys
 BEFORE INSERT ON route_obj
 FOR EACH ROW
-EXECUTE FUNCTION check_aut_num_before_insert_autosys();
+EXECUTE FUNCTION check_route_obj_before_insert_autosys();
 --****************************************************************
 --appied in peer
 CREATE OR REPLACE FUNCTION check_peer_before_insert_autosys()
@@ -187,6 +187,38 @@ CREATE TRIGGER trigger_before_insert_peer
 BEFORE INSERT ON peer
 FOR EACH ROW
 EXECUTE FUNCTION check_peer_before_insert_autosys();
+--*******************************************************************
+--check tier1 before insert autonomous_system
+CREATE OR REPLACE FUNCTION check_tier1_before_insert_autosys()
+RETURNS TRIGGER AS $$
+BEGIN
+  IF NOT EXISTS (SELECT 1 FROM autonomous_system WHERE as_num = NEW.as_num) THEN
+    INSERT INTO autonomous_system (as_num) VALUES (NEW.as_num);
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_before_insert_tier1_autosys
+BEFORE INSERT ON tier1
+FOR EACH ROW
+EXECUTE FUNCTION check_tier1_before_insert_autosys();
+--*******************************************************************
+--check as_organization before insert autonomous_system
+CREATE OR REPLACE FUNCTION check_asorg_before_insert_autosys()
+RETURNS TRIGGER AS $$
+BEGIN
+  IF NOT EXISTS (SELECT 1 FROM autonomous_system WHERE as_num = NEW.as_num) THEN
+    INSERT INTO autonomous_system (as_num) VALUES (NEW.as_num);
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_before_insert_asorg_autosys
+BEFORE INSERT ON as_organization
+FOR EACH ROW
+EXECUTE FUNCTION check_asorg_before_insert_autosys();
 
 
 --*******************************************************************